@@ -0,0 +1,185 @@
+use crate::contract::{column_string, document_value};
+use crate::display::{ValueFormat, NULL_MARKER};
+use crate::sql::{AggregateFunction, SelectColumn};
+use ciborium::ser::into_writer;
+use ciborium::value::Value;
+use indexmap::IndexMap;
+use prettytable::{Cell, Row, Table};
+use rs_drive::contract::{document::Document, DocumentType};
+use std::cmp::Ordering;
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(integer) => Some(i128::from(*integer) as f64),
+        Value::Float(float) => Some(*float),
+        _ => None,
+    }
+}
+
+/// Orders numbers by value, text and bytes lexicographically, other values do not compare.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(i128::from(*a).cmp(&i128::from(*b))),
+        (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+        (Value::Bytes(a), Value::Bytes(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => number(a)?.partial_cmp(&number(b)?),
+    }
+}
+
+fn extreme(values: &[Value], wanted: Ordering) -> Result<Option<&Value>, String> {
+    let mut extreme: Option<&Value> = None;
+    for value in values {
+        extreme = match extreme {
+            None => Some(value),
+            Some(current) => match compare(value, current) {
+                Some(ordering) if ordering == wanted => Some(value),
+                Some(_) => Some(current),
+                None => return Err("values of different types can not be compared".to_string()),
+            },
+        };
+    }
+    Ok(extreme)
+}
+
+fn sum(values: &[Value]) -> Result<String, String> {
+    let integers: Option<i128> = values.iter().try_fold(0_i128, |sum, value| match value {
+        Value::Integer(integer) => sum.checked_add(i128::from(*integer)),
+        _ => None,
+    });
+    if let Some(sum) = integers {
+        return Ok(sum.to_string());
+    }
+    let mut sum = 0_f64;
+    for value in values {
+        sum += number(value).ok_or("only numbers can be added")?;
+    }
+    Ok(sum.to_string())
+}
+
+fn average(values: &[Value]) -> Result<String, String> {
+    if values.is_empty() {
+        return Ok(NULL_MARKER.to_string());
+    }
+    let mut sum = 0_f64;
+    for value in values {
+        sum += number(value).ok_or("only numbers can be averaged")?;
+    }
+    Ok((sum / values.len() as f64).to_string())
+}
+
+fn aggregate_cell(
+    document_type: &DocumentType,
+    documents: &[&Document],
+    function: AggregateFunction,
+    column: Option<&String>,
+    format: ValueFormat,
+) -> Result<String, String> {
+    let column = match column {
+        None => return Ok(documents.len().to_string()),
+        Some(column) => column,
+    };
+    let values: Vec<Value> = documents
+        .iter()
+        .filter_map(|document| document_value(document, column.as_str()))
+        .filter(|value| !value.is_null())
+        .collect();
+    let extreme_cell =
+        |extreme: Option<&Value>| column_string(document_type, column, extreme, format);
+    match function {
+        AggregateFunction::Count => Ok(values.len().to_string()),
+        AggregateFunction::Min => extreme(&values, Ordering::Less).map(extreme_cell),
+        AggregateFunction::Max => extreme(&values, Ordering::Greater).map(extreme_cell),
+        AggregateFunction::Sum => sum(&values),
+        AggregateFunction::Avg => average(&values),
+    }
+}
+
+/// Groups the documents on the group by columns in the order they were returned, without a
+/// group by every document falls in a single group.
+fn groups<'a>(
+    documents: &'a [Document],
+    group_by: &[String],
+) -> IndexMap<Vec<u8>, (Vec<Option<Value>>, Vec<&'a Document>)> {
+    let mut groups: IndexMap<Vec<u8>, (Vec<Option<Value>>, Vec<&Document>)> = IndexMap::new();
+    if group_by.is_empty() {
+        groups.insert(vec![], (vec![], documents.iter().collect()));
+        return groups;
+    }
+    for document in documents.iter() {
+        let values: Vec<Option<Value>> = group_by
+            .iter()
+            .map(|column| document_value(document, column.as_str()))
+            .collect();
+        let mut key = vec![];
+        let key_values = values
+            .iter()
+            .map(|value| value.clone().unwrap_or(Value::Null))
+            .collect();
+        into_writer(&Value::Array(key_values), &mut key).expect("expected to serialize values");
+        groups
+            .entry(key)
+            .or_insert_with(|| (values, vec![]))
+            .1
+            .push(document);
+    }
+    groups
+}
+
+/// Prints group by columns and aggregates of the documents. Drive only returns documents, so
+/// aggregates are computed here, over every document the query matched.
+pub fn print_aggregates(
+    document_type: &DocumentType,
+    documents: &[Document],
+    columns: &[SelectColumn],
+    group_by: &[String],
+    format: ValueFormat,
+) {
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        columns
+            .iter()
+            .map(|column| Cell::new(column.header().as_str()))
+            .collect(),
+    ));
+    for (values, group_documents) in groups(documents, group_by).values() {
+        let mut cells = vec![];
+        for column in columns {
+            let cell = match column {
+                SelectColumn::Column(column) => {
+                    let position = group_by
+                        .iter()
+                        .position(|group_column| group_column == column)
+                        .expect("expected selected columns to be grouped");
+                    Ok(column_string(
+                        document_type,
+                        column,
+                        values[position].as_ref(),
+                        format,
+                    ))
+                }
+                SelectColumn::Aggregate(function, column) => aggregate_cell(
+                    document_type,
+                    group_documents,
+                    *function,
+                    column.as_ref(),
+                    format,
+                ),
+            };
+            match cell {
+                Ok(cell) => cells.push(Cell::new(cell.as_str())),
+                Err(e) => {
+                    println!("### ERROR! Could not aggregate {}", column.header());
+                    println!("### Info {}", e);
+                    return;
+                }
+            }
+        }
+        table.add_row(Row::new(cells));
+    }
+    println!(
+        "Computed locally over {} documents, not by Drive",
+        documents.len()
+    );
+    table.printstd();
+}
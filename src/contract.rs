@@ -1,4 +1,17 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::aggregate::print_aggregates;
+use crate::date::{date_columns, parse_date, timestamp_seconds, timestamp_value, DisplayTimezone};
+use crate::display::{self, check_columns, take_flag, value_string, ValueFormat, NULL_MARKER};
+use crate::export;
+use crate::grove;
+use crate::refund::{add_epoch_bytes, print_refunds};
+use crate::registry::{print_contracts, prompt_use, ContractRegistry};
+use crate::session::{
+    prompt_clock, prompt_epoch, prompt_identity, prompt_seed, prompt_whoami, Session,
+};
+use crate::sql::{parse_select, resolve_literals, Projection, Select, SelectColumn};
+use crate::upgrade::{prompt_diff_contract, prompt_upgrade};
+use crate::whatif;
+use chrono::{DateTime, Utc};
 use ciborium::ser::into_writer;
 use ciborium::value::{Integer as cborInteger, Value};
 use indexmap::IndexMap;
@@ -10,7 +23,7 @@ use rand::{Rng, SeedableRng};
 use rand_distr::num_traits::Pow;
 use rs_drive::common;
 use rs_drive::contract::types::DocumentFieldType;
-use rs_drive::contract::{Contract, document::Document, DocumentType};
+use rs_drive::contract::{Contract, document::Document, DocumentType, Index};
 use rs_drive::drive::object_size_info::DocumentInfo::DocumentAndSerialization;
 use rs_drive::drive::object_size_info::{DocumentAndContractInfo, DocumentInfo};
 use rs_drive::drive::Drive;
@@ -28,32 +41,168 @@ use tempdir::TempDir;
 
 pub const DASH_PRICE: f64 = 100.0;
 
-fn print_contract_format(contract: &Contract) {
+pub const QUERY_PAGE_SIZE: u16 = 1000;
+
+/// Documents shown before confirming a bulk delete
+const DELETE_PREVIEW_ROWS: usize = 10;
+
+/// Commands changing the state, after which the root hash is shown when asked for
+const MUTATING_COMMANDS: [&str; 10] = [
+    "pop ", "popfull ", "pf ", "benchpop ", "bp ", "insert ", "i ", "update ", "delete ",
+    "upgrade ",
+];
+
+/// Whether the command changes the state, the root hash is shown after it when asked for.
+pub fn is_mutating_command(input: &str) -> bool {
+    MUTATING_COMMANDS
+        .iter()
+        .any(|command| input.starts_with(command))
+}
+
+/// Short name of a field type with its bounds, like `string(3..63)`.
+fn field_type_name(field_type: &DocumentFieldType) -> String {
+    let bounds = |min: &Option<usize>, max: &Option<usize>| {
+        format!(
+            "{}..{}",
+            min.map_or("".to_string(), |min| min.to_string()),
+            max.map_or("".to_string(), |max| max.to_string())
+        )
+    };
+    match field_type {
+        DocumentFieldType::Integer => "integer".to_string(),
+        DocumentFieldType::Number => "number".to_string(),
+        DocumentFieldType::Boolean => "boolean".to_string(),
+        DocumentFieldType::Date => "date".to_string(),
+        DocumentFieldType::String(min, max) => format!("string({})", bounds(min, max)),
+        DocumentFieldType::ByteArray(min, max) => format!("byteArray({})", bounds(min, max)),
+        DocumentFieldType::Object(_) => "object".to_string(),
+        DocumentFieldType::Array(_) => "array".to_string(),
+    }
+}
+
+fn byte_size_string(size: Option<usize>) -> String {
+    size.map_or("-".to_string(), |size| size.to_string())
+}
+
+/// Type and byte size of the system fields indices may use.
+fn system_field_type(name: &str) -> Option<(&'static str, usize)> {
+    match name {
+        "$id" | "$ownerId" => Some(("identifier", 32)),
+        "$createdAt" | "$updatedAt" => Some(("date", 8)),
+        _ => None,
+    }
+}
+
+fn print_document_type_format(
+    drive: &Drive,
+    contract: &Contract,
+    name: &str,
+    document_type: &DocumentType,
+) {
+    let indices_with = |property_name: &str| {
+        document_type
+            .indices
+            .iter()
+            .enumerate()
+            .filter(|(_, index)| {
+                index
+                    .properties
+                    .iter()
+                    .any(|index_property| index_property.name == property_name)
+            })
+            .map(|(i, _)| format!("#{}", i))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("property"),
+        Cell::new("type"),
+        Cell::new("required"),
+        Cell::new("min bytes"),
+        Cell::new("max bytes"),
+        Cell::new("indices"),
+    ]));
+    // the id and owner id are always stored with the document
+    let mut worst_case_size = Some(64);
+    for property_name in document_type.properties.keys().sorted() {
+        let document_field = document_type.properties.get(property_name).unwrap();
+        let max_byte_size = document_field.document_type.max_byte_size();
+        worst_case_size = worst_case_size.zip(max_byte_size).map(|(size, max)| size + max);
+        table.add_row(Row::new(vec![
+            Cell::new(property_name),
+            Cell::new(field_type_name(&document_field.document_type).as_str()),
+            Cell::new(if document_field.required { "yes" } else { "no" }),
+            Cell::new(byte_size_string(document_field.document_type.min_byte_size()).as_str()),
+            Cell::new(byte_size_string(max_byte_size).as_str()),
+            Cell::new(indices_with(property_name.as_str()).as_str()),
+        ]));
+    }
+    let system_fields = document_type
+        .indices
+        .iter()
+        .flat_map(|index| index.properties.iter())
+        .map(|index_property| index_property.name.as_str())
+        .filter_map(|name| system_field_type(name).map(|field_type| (name, field_type)))
+        .unique_by(|(name, _)| *name);
+    for (system_field, (type_name, size)) in system_fields {
+        table.add_row(Row::new(vec![
+            Cell::new(system_field),
+            Cell::new(type_name),
+            Cell::new("yes"),
+            Cell::new(size.to_string().as_str()),
+            Cell::new(size.to_string().as_str()),
+            Cell::new(indices_with(system_field).as_str()),
+        ]));
+    }
+
+    println!("## {}", name);
+    table.printstd();
+    for (i, index) in document_type.indices.iter().enumerate() {
+        let unique = if index.unique { " : unique" } else { "" };
+        println!("#### i #{}: {}{}", i, index_name(index), unique);
+    }
+    match worst_case_size {
+        Some(size) => println!("Worst case document size: {} bytes", size),
+        None => println!("Worst case document size: unbounded"),
+    }
+    print_worst_case_fees(drive, contract, name);
+}
+
+fn print_contract_format(drive: &Drive, contract: &Contract) {
     for (document_type_name, document_type) in contract.document_types.iter() {
-        println!("## {}", document_type_name);
-        for property_name in document_type.properties.keys().sorted() {
-            let document_field = document_type.properties.get(property_name).unwrap();
-            println!("#### {} : {:#?}", property_name, document_field);
-        }
-        for index in document_type.indices.iter() {
-            let index_strings: Vec<&str> = index
-                .properties
-                .iter()
-                .map(|index_property| index_property.name.as_str())
-                .collect();
-            let unique = if index.unique { " : unique" } else { "" };
-            println!("#### i: {}{}", index_strings.join("/"), unique);
-        }
+        print_document_type_format(drive, contract, document_type_name, document_type);
+        println!();
+    }
+}
+
+fn prompt_view(input: String, drive: &Drive, contract: &Contract, contract_path: Option<&str>) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    match args.get(1) {
+        None => print_contract_format(drive, contract),
+        Some(&"--json") => export::print_contract_json(drive, contract, contract_path),
+        Some(&"--cbor") => export::prompt_write_cbor(args.get(2).copied(), drive, contract),
+        Some(option) => println!("### ERROR! Unknown view option {}", option),
     }
 }
 
-fn print_contract_options(_contract: &Contract) {
+pub(crate) fn index_name(index: &Index) -> String {
+    index
+        .properties
+        .iter()
+        .map(|index_property| index_property.name.as_str())
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+pub fn print_contract_options(_contract: &Contract) {
     println!();
     println!("#########################################################");
     println!("### You have the following options for this contract: ###");
     println!("#########################################################");
     println!();
-    println!("### view / v                                                      - view contract structure");
+    println!("### view / v <option:--json|--cbor <file>>                        - view contract structure, as json or write the stored cbor");
     println!("### pop <document_type> <number> <option:'include_worst_case'>    - populate with random data a specific document_type"
     );
     println!("### popfull / pf <document_type> <number>                         - populate with random data using all available size a specific document_type"
@@ -67,14 +216,105 @@ fn print_contract_options(_contract: &Contract) {
         "### dryinsert <document_type> <field_0> <field_1> .. <field_n>   - add a specific item"
     );
     println!(
-        "### delete <document_type> <id>                                   - remove an item by id"
+        "### update <document_type> <id> <field>=<value> ..                - change values of an item"
+    );
+    println!(
+        "###                                                               - dates can be ISO-8601 or relative like now, -2d, +1h"
+    );
+    println!(
+        "### delete <document_type> <id> <option:--dry>                    - remove an item by id, --dry only estimates fees and refunds"
     );
-    println!("### all <document_type> <[sortBy1,sortBy2...]> <limit>            - get all people sorted by defined fields");
     println!(
-        "### select <sqlQuery>                                             - sql like query on the system"
+        "### delete from <document_type> where .. <option:--dry>           - remove every matching item after confirming"
+    );
+    println!(
+        "### get <document_type> <id>                                      - get an item by id with all of its values"
+    );
+    println!(
+        "### checkunique <document_type>                                   - scan stored items for unique index violations"
+    );
+    println!("### all <document_type> <[sortBy1,sortBy2...]> <limit> <option:--full>");
+    println!("###                                                                 - get all items sorted by defined fields, --full does not truncate");
+    println!(
+        "### select <sqlQuery> <option:--full>                             - sql like query on the system, from <alias.document_type> for other contracts"
+    );
+    println!(
+        "###                                                               - select a,b.c,$id,$ownerId,$createdAt or count(*) instead of *"
+    );
+    println!(
+        "###                                                               - count, min, max, sum, avg and group by are computed locally"
+    );
+    println!(
+        "### show <row>                                                    - expand a row of the last results with full values"
     );
     println!("### cost <document_type_name>                                     - get the worst case scenario insertion cost"
     );
+    println!(
+        "### whoami <option:identity_id|none>                              - show or set the identity owning new items"
+    );
+    println!(
+        "### identity new                                                  - create a new identity and use it"
+    );
+    println!(
+        "### clock <option:set <iso time>|advance <duration>|real>         - block time of queries and timestamps of new items"
+    );
+    println!(
+        "### epoch <option:number>                                         - show or set the epoch new items are stored in"
+    );
+    println!(
+        "### seed <option:number|off>                                      - generate the same ids and items when replaying commands"
+    );
+    println!(
+        "### epochs                                                        - stored documents and bytes by the epoch they were written in"
+    );
+    println!(
+        "### tree <option:ls|cd|cat|pwd> <option:path>                     - browse how Drive stores the contract in GroveDB"
+    );
+    println!(
+        "### roothash <option:on|off>                                      - show the GroveDB root hash, or always after changes"
+    );
+    println!(
+        "### fingerprint <document_type>                                   - show the hash of the document type subtree"
+    );
+    println!(
+        "### size <option:document_type>                                   - storage used by documents and each index"
+    );
+    println!(
+        "### save <sqlQuery>                                               - save a query for whatif checks"
+    );
+    println!(
+        "### queries                                                       - list the saved queries"
+    );
+    println!(
+        "### whatif <document_type> <+a,b|+!a,b|-a,b> <option:sample>      - estimate adding or removing an index"
+    );
+    println!(
+        "### mine <document_type>                                          - get the items owned by the session identity"
+    );
+    println!(
+        "### display <option:depth <n>|width <n>|bytes <auto|base58|hex|base64>|timezone <utc|local|+hh:mm>>"
+    );
+    println!(
+        "###                                                               - how nested values, text, byte arrays and dates are shown"
+    );
+    println!(
+        "### columns <document_type> <option:a,b.c,..|all>                 - choose the columns shown in results"
+    );
+    println!(
+        "### contracts                                                     - list the loaded contracts"
+    );
+    println!(
+        "### use <alias>                                                   - switch to another loaded contract"
+    );
+    println!(
+        "### diffcontract <old contract path> <new contract path>          - compare two versions of a contract"
+    );
+    println!(
+        "### upgrade <new contract path>                                   - apply a new version of this contract and check stored items"
+    );
+    println!(
+        "### exit                                                          - go back, keeping the contract loaded"
+    );
     println!();
 }
 
@@ -84,8 +324,11 @@ pub fn populate_with_documents(
     document_type: &DocumentType,
     contract: &Contract,
     apply: bool,
+    session: &Session,
 ) -> Result<(i64, u64), Error> {
-    let storage_flags = StorageFlags { epoch: 0 };
+    let storage_flags = StorageFlags {
+        epoch: session.epoch,
+    };
     let db_transaction = drive.grove.start_transaction();
     let mut storage_fee = 0;
     let mut processing_fee = 0;
@@ -103,7 +346,7 @@ pub fn populate_with_documents(
                 owner_id: None,
             },
             false,
-            0.0,
+            session.block_time().unwrap_or(0f64),
             apply,
             Some(&db_transaction),
         )?;
@@ -114,9 +357,199 @@ pub fn populate_with_documents(
     Ok((storage_fee, processing_fee))
 }
 
+/// Gets a value from a document, `$id` and `$ownerId` are resolved to the system fields and
+/// dotted paths like `records.dashUniqueIdentityId` walk into nested maps.
+pub fn document_value(document: &Document, path: &str) -> Option<Value> {
+    match path {
+        "$id" => Some(Value::Bytes(document.id.to_vec())),
+        "$ownerId" => Some(Value::Bytes(document.owner_id.to_vec())),
+        _ => {
+            let mut keys = path.split('.');
+            let mut value = document.properties.get(keys.next()?)?;
+            for key in keys {
+                value = match value {
+                    Value::Map(map) => map.iter().find_map(|(map_key, map_value)| match map_key {
+                        Value::Text(text) if text == key => Some(map_value),
+                        _ => None,
+                    })?,
+                    _ => return None,
+                };
+            }
+            Some(value.clone())
+        }
+    }
+}
+
+/// Fetches every stored document of a document type, a page at a time along the primary key.
+pub fn all_documents(
+    drive: &Drive,
+    contract: &Contract,
+    document_type: &DocumentType,
+) -> Result<Vec<Document>, Error> {
+    let query = DriveQuery {
+        contract,
+        document_type,
+        internal_clauses: InternalClauses::default(),
+        offset: 0,
+        limit: QUERY_PAGE_SIZE,
+        order_by: IndexMap::new(),
+        start_at: None,
+        start_at_included: false,
+        block_time: None,
+    };
+    execute_paged(drive, query).map(|(documents, _)| documents)
+}
+
+/// Runs a query once, returning its documents and processing fee.
+fn execute_documents(drive: &Drive, query: DriveQuery) -> Result<(Vec<Document>, u64), Error> {
+    let (results, _, processing_fee) = query.execute_no_proof(drive, None)?;
+    let documents = results
+        .into_iter()
+        .map(|result| Document::from_cbor(result.as_slice(), None, None))
+        .collect::<Result<Vec<Document>, Error>>()?;
+    Ok((documents, processing_fee))
+}
+
+/// Runs a query again from the last document of each full page, so every match is returned
+/// whatever the query limit. The offset only skips documents before the first page.
+pub fn execute_paged(
+    drive: &Drive,
+    mut query: DriveQuery,
+) -> Result<(Vec<Document>, u64), Error> {
+    let mut documents = vec![];
+    let mut processing_fee = 0;
+    loop {
+        let (results, _, page_processing_fee) = query.execute_no_proof(drive, None)?;
+        processing_fee += page_processing_fee;
+        let page_len = results.len();
+        for result in results {
+            let document = Document::from_cbor(result.as_slice(), None, None)?;
+            query.start_at = Some(document.id);
+            documents.push(document);
+        }
+        query.offset = 0;
+        query.start_at_included = false;
+        if page_len == 0 || page_len < query.limit as usize {
+            break;
+        }
+    }
+    Ok((documents, processing_fee))
+}
+
+/// Looks for stored documents that would violate one of the unique indices if the document was
+/// inserted, returning the index and the id of the document already holding its values.
+fn unique_index_conflicts(
+    drive: &Drive,
+    contract: &Contract,
+    document_type: &DocumentType,
+    document: &Document,
+) -> Result<Vec<(String, [u8; 32])>, Error> {
+    let mut conflicts = vec![];
+    'indices: for index in document_type.indices.iter().filter(|index| index.unique) {
+        let mut equal_clauses = BTreeMap::new();
+        for index_property in index.properties.iter() {
+            match document_value(document, index_property.name.as_str()) {
+                Some(value) => {
+                    equal_clauses.insert(
+                        index_property.name.clone(),
+                        WhereClause {
+                            field: index_property.name.clone(),
+                            operator: WhereOperator::Equal,
+                            value,
+                        },
+                    );
+                }
+                // documents missing a property of the index are not constrained by it
+                None => continue 'indices,
+            }
+        }
+        let query = DriveQuery {
+            contract,
+            document_type,
+            internal_clauses: InternalClauses {
+                equal_clauses,
+                ..Default::default()
+            },
+            offset: 0,
+            limit: 2,
+            order_by: IndexMap::new(),
+            start_at: None,
+            start_at_included: false,
+            block_time: None,
+        };
+        let (results, _, _) = query.execute_no_proof(drive, None)?;
+        for result in results {
+            let existing = Document::from_cbor(result.as_slice(), None, None)?;
+            if existing.id != document.id {
+                conflicts.push((index_name(index), existing.id));
+            }
+        }
+    }
+    Ok(conflicts)
+}
+
+fn prompt_check_unique(input: String, drive: &Drive, contract: &Contract) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 {
+        println!("### ERROR! One parameter should be provided");
+        return;
+    }
+    let document_type_name = args.get(1).unwrap();
+    let document_type = match contract.document_type_for_name(document_type_name) {
+        Ok(document_type) => document_type,
+        Err(_) => {
+            println!("### ERROR! Document type does not exist");
+            return;
+        }
+    };
+    let documents = match all_documents(drive, contract, document_type) {
+        Ok(documents) => documents,
+        Err(e) => {
+            println!("### ERROR! Could not fetch documents");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    println!("Scanned {} documents", documents.len());
+    for index in document_type.indices.iter().filter(|index| index.unique) {
+        let mut holders: BTreeMap<Vec<u8>, Vec<[u8; 32]>> = BTreeMap::new();
+        for document in documents.iter() {
+            let values: Option<Vec<Value>> = index
+                .properties
+                .iter()
+                .map(|index_property| document_value(document, index_property.name.as_str()))
+                .collect();
+            if let Some(values) = values {
+                let mut key = vec![];
+                into_writer(&Value::Array(values), &mut key).expect("expected to serialize values");
+                holders.entry(key).or_default().push(document.id);
+            }
+        }
+        let violations: Vec<&Vec<[u8; 32]>> =
+            holders.values().filter(|ids| ids.len() > 1).collect();
+        if violations.is_empty() {
+            println!("Unique index {}: no violations", index_name(index));
+        } else {
+            println!(
+                "Unique index {}: {} violations",
+                index_name(index),
+                violations.len()
+            );
+            for ids in violations {
+                let ids: Vec<String> = ids
+                    .iter()
+                    .map(|id| bs58::encode(id).into_string())
+                    .collect();
+                println!("#### {}", ids.join(", "));
+            }
+        }
+    }
+}
+
 fn queries_for_document_type<'a>(
     contract: &'a Contract,
     document_type: &'a DocumentType,
+    block_time: Option<f64>,
 ) -> Vec<DriveQuery<'a>> {
     let mut drive_queries = vec![];
     let mut rng = rand::rngs::StdRng::from_entropy();
@@ -152,7 +585,7 @@ fn queries_for_document_type<'a>(
                 order_by,
                 start_at: None,
                 start_at_included: false,
-                block_time: None,
+                block_time,
             };
             drive_queries.push(query);
         }
@@ -164,8 +597,9 @@ fn execute_random_queries_for_document_type(
     drive: &Drive,
     contract: &Contract,
     document_type: &DocumentType,
+    block_time: Option<f64>,
 ) -> (usize, u64, f64) {
-    let queries = queries_for_document_type(contract, document_type);
+    let queries = queries_for_document_type(contract, document_type, block_time);
     let start_time = SystemTime::now();
     let mut total_count: u64 = 0;
     let queries_len = queries.len();
@@ -182,6 +616,7 @@ fn execute_random_queries_for_document_type(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn populate_many(
     count: u32,
     drive: &Drive,
@@ -190,16 +625,22 @@ fn populate_many(
     i: Option<u32>,
     export_csv: bool,
     include_worst_case: bool,
+    session: &mut Session,
 ) {
-    let documents = document_type.random_documents(count, None);
+    let documents = document_type.random_documents(count, session.next_seed());
     if include_worst_case {
-        populate_documents_with_descriptions(documents.clone(), drive, contract, document_type, i, export_csv, false);
+        populate_documents_with_descriptions(documents.clone(), drive, contract, document_type, i, export_csv, false, session);
     }
-    populate_documents_with_descriptions(documents, drive, contract, document_type, i, export_csv, true);
+    populate_documents_with_descriptions(documents, drive, contract, document_type, i, export_csv, true, session);
+}
+
+/// Converts credits to their price in cents.
+pub fn credits_in_cents(credits: f64) -> f64 {
+    credits * 10_f64.pow(-9) * DASH_PRICE
 }
 
-fn print_fees(storage_fee: i64, processing_fee: u64, count: u32) {
-    let cent_cost = (storage_fee as f64) * 10_f64.pow(-9) * DASH_PRICE;
+pub fn print_fees(storage_fee: i64, processing_fee: u64, count: u32) {
+    let cent_cost = credits_in_cents(storage_fee as f64);
     if cent_cost < 100f64 {
         if count > 1 {
             println!(
@@ -233,7 +674,7 @@ fn print_fees(storage_fee: i64, processing_fee: u64, count: u32) {
         }
     }
 
-    let processing_cent_cost = (processing_fee as f64) * 10_f64.pow(-9) * DASH_PRICE;
+    let processing_cent_cost = credits_in_cents(processing_fee as f64);
     if count > 1 {
         println!(
             "Processing fee: {} ({:.2}¢ | {:.2}¢ each)",
@@ -245,12 +686,13 @@ fn print_fees(storage_fee: i64, processing_fee: u64, count: u32) {
         println!(
             "Processing fee: {} ({:.2}¢)",
             processing_fee,
-            (processing_fee as f64) * 10_f64.pow(-9) * DASH_PRICE
+            processing_cent_cost
         );
     }
 
 }
 
+#[allow(clippy::too_many_arguments)]
 fn populate_documents_with_descriptions(
     documents: Vec<Document>,
     drive: &Drive,
@@ -259,11 +701,12 @@ fn populate_documents_with_descriptions(
     i: Option<u32>,
     export_csv: bool,
     apply: bool,
+    session: &Session,
 ) {
     let start_time = SystemTime::now();
     let len = documents.len() as u32;
     let (storage_fee, processing_fee) =
-        populate_with_documents(documents, drive, document_type, contract, apply)
+        populate_with_documents(documents, drive, document_type, contract, apply, session)
             .expect("populate returned an error");
     let mut insertion_time = 0f64;
     if let Ok(n) = SystemTime::now().duration_since(start_time) {
@@ -277,7 +720,7 @@ fn populate_documents_with_descriptions(
         }
     }
     let (queries_len, total_count, query_time) =
-        execute_random_queries_for_document_type(drive, contract, document_type);
+        execute_random_queries_for_document_type(drive, contract, document_type, session.block_time());
     if export_csv == false {
         println!(
             "{} {} returned {} values in: {}",
@@ -291,7 +734,7 @@ fn populate_documents_with_descriptions(
     }
 }
 
-fn prompt_populate(input: String, drive: &Drive, contract: &Contract) {
+fn prompt_populate(input: String, drive: &Drive, contract: &Contract, session: &mut Session) {
     let args: Vec<&str> = input.split_whitespace().collect();
     if args.len() != 3 && args.len() != 4 {
         println!("### ERROR! At max three parameters should be provided");
@@ -304,7 +747,7 @@ fn prompt_populate(input: String, drive: &Drive, contract: &Contract) {
                 Ok(value) => {
                     let include_worst_case = args.get(3).map_or(false, |csv| csv.eq(&"include_worst_case"));
                     if value > 0 && value <= 10000 {
-                        populate_many(value, drive, contract, document_type, None, false, include_worst_case);
+                        populate_many(value, drive, contract, document_type, None, false, include_worst_case, session);
                     } else {
                         println!("### ERROR! Value must be between 1 and 10000");
                     }
@@ -320,7 +763,7 @@ fn prompt_populate(input: String, drive: &Drive, contract: &Contract) {
     }
 }
 
-fn prompt_bench(input: String, drive: &Drive, contract: &Contract) {
+fn prompt_bench(input: String, drive: &Drive, contract: &Contract, session: &mut Session) {
     let args: Vec<&str> = input.split_whitespace().collect();
     if args.len() != 3 && args.len() != 4 && args.len() != 5 {
         println!("### ERROR! Between two and four parameters should be provided");
@@ -345,6 +788,7 @@ fn prompt_bench(input: String, drive: &Drive, contract: &Contract) {
                                         Some(i as u32),
                                         csv,
                                         false,
+                                        session,
                                     );
                                 }
                                 populate_many(
@@ -355,6 +799,7 @@ fn prompt_bench(input: String, drive: &Drive, contract: &Contract) {
                                     Some(steps_count as u32),
                                     csv,
                                     false,
+                                    session,
                                 );
                             }
                             Err(_) => {
@@ -376,7 +821,7 @@ fn prompt_bench(input: String, drive: &Drive, contract: &Contract) {
     }
 }
 
-fn prompt_populate_full(input: String, drive: &Drive, contract: &Contract) {
+fn prompt_populate_full(input: String, drive: &Drive, contract: &Contract, session: &mut Session) {
     let args: Vec<&str> = input.split_whitespace().collect();
     if args.len() != 3 {
         println!("### ERROR! Two parameter should be provided");
@@ -387,10 +832,10 @@ fn prompt_populate_full(input: String, drive: &Drive, contract: &Contract) {
             Ok(document_type) => match count_str.parse::<u32>() {
                 Ok(value) => {
                     if value > 0 && value <= 10000 {
-                        let documents = document_type.random_filled_documents(value, None);
+                        let documents = document_type.random_filled_documents(value, session.next_seed());
                         let start_time = SystemTime::now();
                         let (storage_fee, processing_fee) =
-                            populate_with_documents(documents, drive, document_type, contract, true)
+                            populate_with_documents(documents, drive, document_type, contract, true, session)
                                 .expect("populate returned an error");
                         if let Ok(n) = SystemTime::now().duration_since(start_time) {
                             print_fees(storage_fee, processing_fee, value as u32);
@@ -411,8 +856,16 @@ fn prompt_populate_full(input: String, drive: &Drive, contract: &Contract) {
     }
 }
 
-fn prompt_insert(input: String, drive: &Drive, contract: &Contract) {
-    let storage_flags = StorageFlags { epoch: 0 };
+fn prompt_insert(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    session: &mut Session,
+    timestamp_fields: Option<&BTreeMap<String, Vec<String>>>,
+) {
+    let storage_flags = StorageFlags {
+        epoch: session.epoch,
+    };
     let args = input.split_whitespace();
     let count = &args.count();
     if *count < 2 {
@@ -433,20 +886,30 @@ fn prompt_insert(input: String, drive: &Drive, contract: &Contract) {
                         fields_count + 2
                     );
                 } else {
+                    let timezone = session.display.timezone;
                     let mut hashmap: HashMap<String, Value> = HashMap::new();
                     for (i, property_name) in
-                        (2..=*fields_count).zip(&mut document_type.properties.keys().sorted())
+                        (2..*fields_count + 2).zip(&mut document_type.properties.keys().sorted())
                     {
                         let value = split.get(i).unwrap();
                         let property_field = document_type.properties.get(property_name).unwrap();
-                        let value: Value = property_field.document_type
-                            .value_from_string(value)
-                            .expect("expected to get a value");
-                        hashmap.insert(property_name.clone(), value);
+                        match property_value(&property_field.document_type, value, session.now(), timezone) {
+                            Ok(value) => {
+                                hashmap.insert(property_name.clone(), value);
+                            }
+                            Err(e) => {
+                                println!("### ERROR! Invalid value for {}", property_name);
+                                println!("### Info {}", e);
+                                return;
+                            }
+                        }
                     }
-                    let mut rng = rand::rngs::StdRng::from_entropy();
-                    let id = Vec::from(rng.gen::<[u8; 32]>());
-                    let owner_id = Vec::from(rng.gen::<[u8; 32]>());
+                    let now = timestamp_value(session.now());
+                    for field in declared_timestamps(timestamp_fields, document_type_name) {
+                        hashmap.insert(field.clone(), now.clone());
+                    }
+                    let id = Vec::from(session.rng().gen::<[u8; 32]>());
+                    let owner_id = Vec::from(session.owner_id_or_random());
                     hashmap.insert("$id".to_string(), Value::Bytes(id));
                     hashmap.insert("$ownerId".to_string(), Value::Bytes(owner_id));
 
@@ -458,34 +921,56 @@ fn prompt_insert(input: String, drive: &Drive, contract: &Contract) {
                     let document = Document::from_cbor(document_cbor.as_slice(), None, None)
                         .expect("document should be properly deserialized");
 
+                    match unique_index_conflicts(drive, contract, document_type, &document) {
+                        Ok(conflicts) if !conflicts.is_empty() => {
+                            for (index_name, existing_id) in conflicts {
+                                println!(
+                                    "### ERROR! Unique index {} is already used by document {}",
+                                    index_name,
+                                    bs58::encode(existing_id).into_string()
+                                );
+                            }
+                            return;
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            println!("### ERROR! Could not check unique indices");
+                            println!("### Info {:?}", e);
+                            return;
+                        }
+                    }
+
                     let start_time = SystemTime::now();
                     let db_transaction = drive.grove.start_transaction();
-                    let (storage_fee, processing_fee) = drive
-                        .add_document_for_contract(
-                            DocumentAndContractInfo {
-                                document_info: DocumentAndSerialization((
-                                    &document,
-                                    &document_cbor,
-                                    &storage_flags,
-                                )),
-                                contract,
-                                document_type,
-                                owner_id: None,
-                            },
-                            true,
-                            0f64,
-                            true,
-                            Some(&db_transaction),
-                        )
-                        .expect("document should be inserted");
-                    drive
-                        .grove
-                        .commit_transaction(db_transaction)
-                        .map_err(|err| {
-                            println!("### ERROR! Unable to commit transaction");
-                            println!("### Info {:?}", err);
-                        })
-                        .expect("expected to commit transaction");
+                    let insert_result = drive.add_document_for_contract(
+                        DocumentAndContractInfo {
+                            document_info: DocumentAndSerialization((
+                                &document,
+                                &document_cbor,
+                                &storage_flags,
+                            )),
+                            contract,
+                            document_type,
+                            owner_id: session.owner_id_slice(),
+                        },
+                        true,
+                        session.block_time().unwrap_or(0f64),
+                        true,
+                        Some(&db_transaction),
+                    );
+                    let (storage_fee, processing_fee) = match insert_result {
+                        Ok(fees) => fees,
+                        Err(e) => {
+                            println!("### ERROR! Document could not be inserted");
+                            println!("### Info {:?}", e);
+                            return;
+                        }
+                    };
+                    if let Err(e) = drive.grove.commit_transaction(db_transaction) {
+                        println!("### ERROR! Unable to commit transaction");
+                        println!("### Info {:?}", e);
+                        return;
+                    }
                     if let Ok(n) = SystemTime::now().duration_since(start_time) {
                         print_fees(storage_fee, processing_fee, 1);
                         println!("Time taken: {}", n.as_secs_f64());
@@ -499,168 +984,893 @@ fn prompt_insert(input: String, drive: &Drive, contract: &Contract) {
     }
 }
 
-fn prompt_delete(input: String, drive: &Drive, contract: &Contract) {
-    let args = input.split_whitespace();
-    if args.count() != 3 {
+/// System timestamps a document type of the contract declares, they are set on writes.
+fn declared_timestamps<'a>(
+    timestamp_fields: Option<&'a BTreeMap<String, Vec<String>>>,
+    document_type_name: &str,
+) -> &'a [String] {
+    timestamp_fields
+        .and_then(|timestamp_fields| timestamp_fields.get(document_type_name))
+        .map_or(&[], |fields| fields.as_slice())
+}
+
+/// Reads a property value typed in a command, dates can also be written as ISO-8601 dates or
+/// relative ones like `now` and `-2d`.
+fn property_value(
+    field_type: &DocumentFieldType,
+    text: &str,
+    now: DateTime<Utc>,
+    timezone: DisplayTimezone,
+) -> Result<Value, String> {
+    if let DocumentFieldType::Date = field_type {
+        if let Ok(datetime) = parse_date(text, now, timezone) {
+            return Ok(timestamp_value(datetime));
+        }
+    }
+    field_type
+        .value_from_string(text)
+        .map_err(|e| format!("{:?}", e))
+}
+
+/// Decodes a 32 byte id written in base58, hex or base64.
+pub fn decode_id(encoded_id: &str) -> Option<[u8; 32]> {
+    let decodings = [
+        bs58::decode(encoded_id).into_vec().ok(),
+        hex::decode(encoded_id).ok(),
+        base64::decode(encoded_id).ok(),
+    ];
+    decodings
+        .into_iter()
+        .flatten()
+        .find_map(|bytes| bytes.try_into().ok())
+}
+
+/// Checks that the session identity, when there is one, owns the document before it is changed.
+fn check_session_owner(document: &Document, session: &Session) -> bool {
+    match session.owner_id {
+        Some(owner_id) if owner_id != document.owner_id => {
+            println!(
+                "### ERROR! Document is owned by {}, not by the session identity",
+                bs58::encode(document.owner_id).into_string()
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Deletes documents in one transaction, returning the processing fee. Without `apply` the
+/// costs are only estimated and the transaction is dropped.
+fn delete_documents(
+    drive: &Drive,
+    contract: &Contract,
+    document_type_name: &str,
+    ids: &[[u8; 32]],
+    session: &Session,
+    apply: bool,
+) -> Result<u64, Error> {
+    let db_transaction = drive.grove.start_transaction();
+    let mut processing_fee = 0;
+    for id in ids.iter() {
+        let (_, p) = drive.delete_document_for_contract(
+            id.as_slice(),
+            contract,
+            document_type_name,
+            session.owner_id_slice(),
+            apply,
+            Some(&db_transaction),
+        )?;
+        processing_fee += p;
+    }
+    if apply {
+        drive.grove.commit_transaction(db_transaction)?;
+    }
+    Ok(processing_fee)
+}
+
+fn prompt_delete(input: String, drive: &Drive, contract: &Contract, session: &Session) {
+    let (input, dry) = take_flag(input, "--dry");
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 3 {
         println!("### ERROR! Two parameter should be provided");
-    } else {
-        let split: Vec<String> = input.split_whitespace().map(|s| s.to_string()).collect();
-        let document_type_name = split.get(1).unwrap().as_str();
-        let id_bs58 = split.get(2).unwrap().as_str();
-        let id = bs58::decode(id_bs58).into_vec();
-        if id.is_err() {
+        return;
+    }
+    let document_type_name = args.get(1).unwrap();
+    let id = match decode_id(args.get(2).unwrap()) {
+        Some(id) => id,
+        None => {
             println!("### ERROR! Could not decode id");
+            return;
         }
-        let id = id.unwrap();
-        if drive
-            .delete_document_for_contract(id.as_slice(), contract, document_type_name, None, true, None)
-            .is_err()
-        {
+    };
+    let document_type = match contract.document_type_for_name(document_type_name) {
+        Ok(document_type) => document_type,
+        Err(_) => {
+            println!("### ERROR! Document type does not exist");
+            return;
+        }
+    };
+    let document = match document_for_id(drive, contract, document_type, &id) {
+        Ok(Some((document, _))) => document,
+        Ok(None) => {
+            println!("### ERROR! No {} document with that id", document_type_name);
+            return;
+        }
+        Err(e) => {
+            println!("### ERROR! Could not fetch document");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    if !check_session_owner(&document, session) {
+        return;
+    }
+    let epoch_bytes = match grove::document_epoch_bytes(drive, contract, document_type, &document)
+    {
+        Ok(epoch_bytes) => epoch_bytes,
+        Err(e) => {
+            println!("### ERROR! Could not get the stored document");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    match delete_documents(drive, contract, document_type_name, &[id], session, !dry) {
+        Ok(processing_fee) => {
+            println!(
+                "Processing fee: {} ({:.2}¢)",
+                processing_fee,
+                credits_in_cents(processing_fee as f64)
+            );
+            print_refunds(&epoch_bytes, session.epoch);
+            if dry {
+                println!("Dry run, the document was not deleted");
+            }
+        }
+        Err(e) => {
             println!("### ERROR! Could not delete document");
+            println!("### Info {:?}", e);
         }
     }
 }
 
-fn prompt_query(input: String, drive: &Drive, contract: &Contract) {
-    let query = DriveQuery::from_sql_expr(input.as_str(), &contract).expect("should build query");
-    let results = query.execute_no_proof(&drive, None);
-    if let Ok((results, _, processing_fee)) = results {
-        let documents: Vec<Document> = results
-            .into_iter()
-            .map(|result| {
-                Document::from_cbor(result.as_slice(), None, None)
-                    .expect("we should be able to deserialize the cbor")
-            })
-            .collect();
-        println!("processing fee is {}", processing_fee);
-        print_results(&query.document_type, documents);
-    } else {
-        println!("invalid query, try again");
+pub(crate) fn confirm(rl: &mut Editor<()>, question: &str) -> bool {
+    match rl.readline(format!("{} [y/N] ", question).as_str()) {
+        Ok(answer) => matches!(answer.trim(), "y" | "yes"),
+        Err(_) => false,
     }
 }
 
-fn prompt_cost(input: String, drive: &Drive, contract: &Contract) {
-    let args = input.split_whitespace();
-    if args.count() != 2 {
-        println!("### ERROR! Two parameter should be provided");
+/// Deletes every document matching `delete from <document_type> where ...` in one transaction,
+/// after showing how many match with a preview and asking for confirmation.
+fn prompt_delete_where(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    session: &Session,
+    format: ValueFormat,
+    rl: &mut Editor<()>,
+) {
+    let (input, dry) = take_flag(input, "--dry");
+    let sql = format!("select * {}", input.strip_prefix("delete ").unwrap_or_default());
+    let select = match parse_select(sql.as_str()) {
+        Ok(select) => select,
+        Err(e) => {
+            println!("### ERROR! Invalid query");
+            println!("### Info {}", e);
+            return;
+        }
+    };
+    if select.contract_alias.is_some() {
+        println!("### ERROR! Only documents of the current contract can be deleted");
+        return;
+    }
+    let query = match drive_query(&select, contract, session) {
+        Some(query) => query,
+        None => return,
+    };
+    let document_type = query.document_type;
+    let results = if select.has_limit {
+        execute_documents(drive, query)
     } else {
-        let document_type_name = input.split_whitespace().last().unwrap();
-        let document_type_result = contract.document_type_for_name(document_type_name);
-        match document_type_result {
-            Ok(_) => {
-                match drive.worst_case_fee_for_document_type_with_name(contract, document_type_name)
-                {
-                    Ok((storage_fee, processing_fee)) => {
-                        println!("For {} document type:", document_type_name);
-                        println!(
-                            "Worst case storage fee: {} ({:.2}¢)",
-                            storage_fee,
-                            (storage_fee as f64) * 10_f64.pow(-9) * DASH_PRICE
-                        );
-                        println!(
-                            "Worst case processing fee: {} ({:.2}¢)",
-                            processing_fee,
-                            (processing_fee as f64) * 10_f64.pow(-9) * DASH_PRICE
-                        );
-                    }
-                    Err(e) => {
-                        println!("### ERROR! Could not get worst case fee from contract");
-                    }
-                }
+        execute_paged(drive, query)
+    };
+    let documents = match results {
+        Ok((documents, _)) => documents,
+        Err(e) => {
+            println!("invalid query, try again");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    if documents.is_empty() {
+        println!("No {} documents match", document_type.name);
+        return;
+    }
+    println!("{} {} documents match", documents.len(), document_type.name);
+    let preview_len = documents.len().min(DELETE_PREVIEW_ROWS);
+    let columns = result_columns(document_type, format);
+    print_results(document_type, &documents[..preview_len], &columns, format);
+    if documents.len() > preview_len {
+        println!("... and {} more", documents.len() - preview_len);
+    }
+    if let Some(owner_id) = session.owner_id {
+        let not_owned = documents
+            .iter()
+            .filter(|document| document.owner_id != owner_id)
+            .count();
+        if not_owned > 0 {
+            println!(
+                "### ERROR! {} of the documents are not owned by the session identity",
+                not_owned
+            );
+            return;
+        }
+    }
+    let mut epoch_bytes = BTreeMap::new();
+    for document in documents.iter() {
+        match grove::document_epoch_bytes(drive, contract, document_type, document) {
+            Ok(document_epoch_bytes) => add_epoch_bytes(&mut epoch_bytes, &document_epoch_bytes),
+            Err(e) => {
+                println!("### ERROR! Could not get the stored documents");
+                println!("### Info {:?}", e);
+                return;
             }
-            Err(_) => {
-                println!("### ERROR! Document type does not exist");
+        }
+    }
+    if !dry && !confirm(rl, format!("Delete {} documents?", documents.len()).as_str()) {
+        println!("Nothing was deleted");
+        return;
+    }
+    let ids: Vec<[u8; 32]> = documents.iter().map(|document| document.id).collect();
+    let start_time = SystemTime::now();
+    match delete_documents(
+        drive,
+        contract,
+        document_type.name.as_str(),
+        &ids,
+        session,
+        !dry,
+    ) {
+        Ok(processing_fee) => {
+            let count = ids.len() as f64;
+            let processing_cent_cost = credits_in_cents(processing_fee as f64);
+            println!(
+                "Processing fee: {} ({:.2}¢ | {:.2}¢ each)",
+                processing_fee,
+                processing_cent_cost,
+                processing_cent_cost / count
+            );
+            print_refunds(&epoch_bytes, session.epoch);
+            if dry {
+                println!("Dry run, no document was deleted");
+            } else {
+                println!("Deleted {} documents", ids.len());
             }
+            if let Ok(n) = SystemTime::now().duration_since(start_time) {
+                println!("Time taken: {}", n.as_secs_f64());
+            }
+        }
+        Err(e) => {
+            println!("### ERROR! Could not delete the documents, none were deleted");
+            println!("### Info {:?}", e);
         }
     }
 }
 
-fn reduced_value_string_representation(value: &Value, field_type: &DocumentFieldType) -> String {
-    match value {
-        Value::Integer(integer) => {
-            let i: i128 = integer.clone().try_into().unwrap();
-            format!("{}", i)
+fn prompt_update(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    session: &Session,
+    timestamp_fields: Option<&BTreeMap<String, Vec<String>>>,
+) {
+    let storage_flags = StorageFlags {
+        epoch: session.epoch,
+    };
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() < 4 {
+        println!("### ERROR! A document type, an id and at least one property=value should be provided");
+        return;
+    }
+    let document_type_name = args.get(1).unwrap();
+    let document_type = match contract.document_type_for_name(document_type_name) {
+        Ok(document_type) => document_type,
+        Err(_) => {
+            println!("### ERROR! Document type does not exist");
+            return;
+        }
+    };
+    if !document_type.documents_mutable {
+        println!(
+            "### ERROR! Documents of {} are immutable and can not be updated",
+            document_type_name
+        );
+        return;
+    }
+    let id = match decode_id(args.get(2).unwrap()) {
+        Some(id) => id,
+        None => {
+            println!("### ERROR! Could not decode id");
+            return;
+        }
+    };
+    let mut document = match document_for_id(drive, contract, document_type, &id) {
+        Ok(Some((document, _))) => document,
+        Ok(None) => {
+            println!("### ERROR! No {} document with that id", document_type_name);
+            return;
+        }
+        Err(e) => {
+            println!("### ERROR! Could not fetch document");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    if !check_session_owner(&document, session) {
+        return;
+    }
+    for assignment in args.iter().skip(3) {
+        let (property_name, value_string) = match assignment.split_once('=') {
+            Some(split) => split,
+            None => {
+                println!("### ERROR! Expected property=value, got {}", assignment);
+                return;
+            }
+        };
+        let property_field = match document_type.properties.get(property_name) {
+            Some(property_field) => property_field,
+            None => {
+                println!("### ERROR! Property {} does not exist", property_name);
+                return;
+            }
+        };
+        match property_value(
+            &property_field.document_type,
+            value_string,
+            session.now(),
+            session.display.timezone,
+        ) {
+            Ok(value) => {
+                document.properties.insert(property_name.to_string(), value);
+            }
+            Err(e) => {
+                println!("### ERROR! Invalid value for {}", property_name);
+                println!("### Info {}", e);
+                return;
+            }
+        }
+    }
+    if declared_timestamps(timestamp_fields, document_type_name)
+        .iter()
+        .any(|field| field == "$updatedAt")
+    {
+        document
+            .properties
+            .insert("$updatedAt".to_string(), timestamp_value(session.now()));
+    }
+
+    match unique_index_conflicts(drive, contract, document_type, &document) {
+        Ok(conflicts) if !conflicts.is_empty() => {
+            for (index_name, existing_id) in conflicts {
+                println!(
+                    "### ERROR! Unique index {} is already used by document {}",
+                    index_name,
+                    bs58::encode(existing_id).into_string()
+                );
+            }
+            return;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            println!("### ERROR! Could not check unique indices");
+            println!("### Info {:?}", e);
+            return;
         }
-        Value::Bytes(bytes) => hex::encode(bytes),
-        Value::Float(float) => {
-            match field_type {
-                DocumentFieldType::Date => {
-                    // Convert the timestamp string into an i64
-                    let timestamp = float.floor() as i64;
+    }
 
-                    let nano_seconds = (float * 1000.0) as u64 - (timestamp as u64 * 1000);
+    let document_cbor = document.to_cbor();
+    let start_time = SystemTime::now();
+    // the old version is deleted first so its index entries go away with it
+    let db_transaction = drive.grove.start_transaction();
+    let update_result = drive
+        .delete_document_for_contract(
+            &id,
+            contract,
+            document_type_name,
+            session.owner_id_slice(),
+            true,
+            Some(&db_transaction),
+        )
+        .and_then(|(_, delete_processing_fee)| {
+            let (storage_fee, processing_fee) = drive.add_document_for_contract(
+                DocumentAndContractInfo {
+                    document_info: DocumentAndSerialization((
+                        &document,
+                        &document_cbor,
+                        &storage_flags,
+                    )),
+                    contract,
+                    document_type,
+                    owner_id: session.owner_id_slice(),
+                },
+                false,
+                session.block_time().unwrap_or(0f64),
+                true,
+                Some(&db_transaction),
+            )?;
+            Ok((storage_fee, processing_fee + delete_processing_fee))
+        });
+    let (storage_fee, processing_fee) = match update_result {
+        Ok(fees) => fees,
+        Err(e) => {
+            println!("### ERROR! Document could not be updated");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    if let Err(e) = drive.grove.commit_transaction(db_transaction) {
+        println!("### ERROR! Unable to commit transaction");
+        println!("### Info {:?}", e);
+        return;
+    }
+    if let Ok(n) = SystemTime::now().duration_since(start_time) {
+        print_fees(storage_fee, processing_fee, 1);
+        println!("Time taken: {}", n.as_secs_f64());
+    }
+}
 
-                    // Create a NaiveDateTime from the timestamp
-                    let naive = NaiveDateTime::from_timestamp_opt(timestamp, nano_seconds as u32);
+fn prompt_mine(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    session: &Session,
+    format: ValueFormat,
+) -> Option<(DocumentType, Vec<Document>)> {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 {
+        println!("### ERROR! One parameter should be provided");
+        return None;
+    }
+    let owner_id = match session.owner_id {
+        Some(owner_id) => owner_id,
+        None => {
+            println!("### ERROR! No identity set, use whoami or identity new first");
+            return None;
+        }
+    };
+    let document_type = match contract.document_type_for_name(args.get(1).unwrap()) {
+        Ok(document_type) => document_type,
+        Err(_) => {
+            println!("### ERROR! Document type does not exist");
+            return None;
+        }
+    };
+    let owner_index = document_type.indices.iter().any(|index| {
+        index
+            .properties
+            .first()
+            .map_or(false, |property| property.name == "$ownerId")
+    });
+    let documents = if owner_index {
+        let mut equal_clauses = BTreeMap::new();
+        equal_clauses.insert(
+            "$ownerId".to_string(),
+            WhereClause {
+                field: "$ownerId".to_string(),
+                operator: WhereOperator::Equal,
+                value: Value::Bytes(owner_id.to_vec()),
+            },
+        );
+        let query = DriveQuery {
+            contract,
+            document_type,
+            internal_clauses: InternalClauses {
+                equal_clauses,
+                ..Default::default()
+            },
+            offset: 0,
+            limit: QUERY_PAGE_SIZE,
+            order_by: IndexMap::new(),
+            start_at: None,
+            start_at_included: false,
+            block_time: session.block_time(),
+        };
+        match execute_paged(drive, query) {
+            Ok((documents, processing_fee)) => {
+                println!("processing fee is {}", processing_fee);
+                documents
+            }
+            Err(e) => {
+                println!("### ERROR! Could not query documents by $ownerId");
+                println!("### Info {:?}", e);
+                return None;
+            }
+        }
+    } else {
+        println!("No index starts with $ownerId, filtering all documents by owner");
+        match all_documents(drive, contract, document_type) {
+            Ok(documents) => documents
+                .into_iter()
+                .filter(|document| document.owner_id == owner_id)
+                .collect(),
+            Err(e) => {
+                println!("### ERROR! Could not get documents");
+                println!("### Info {:?}", e);
+                return None;
+            }
+        }
+    };
+    println!("result len: {}", documents.len());
+    let columns = result_columns(document_type, format);
+    print_results(document_type, &documents, &columns, format);
+    Some((document_type.clone(), documents))
+}
 
-                    match naive {
-                        None => {
-                            format!("{}", float)
-                        }
-                        Some(naive) => {
-                            // Create a normal DateTime from the NaiveDateTime
-                            let datetime: DateTime<Utc> = DateTime::from_utc(naive, Utc);
+fn print_full_value(
+    name: &str,
+    path: &str,
+    value: &Value,
+    field_type: Option<&DocumentFieldType>,
+    depth: usize,
+    format: ValueFormat,
+) {
+    let indent = "  ".repeat(depth);
+    match value {
+        Value::Map(map) => {
+            println!("{}{}:", indent, name);
+            for (key, value) in map.iter() {
+                let key = match key {
+                    Value::Text(text) => text.clone(),
+                    _ => value_string(key, path, None, format),
+                };
+                let value_path = format!("{}.{}", path, key);
+                print_full_value(key.as_str(), value_path.as_str(), value, None, depth + 1, format);
+            }
+        }
+        Value::Array(array) => {
+            println!("{}{}: [{}]", indent, name, array.len());
+            for (i, value) in array.iter().enumerate() {
+                print_full_value(format!("{}", i).as_str(), path, value, None, depth + 1, format);
+            }
+        }
+        Value::Text(text) => println!("{}{}: {}", indent, name, text),
+        Value::Tag(tag, value) => print_full_value(
+            format!("{} (tag {})", name, tag).as_str(),
+            path,
+            value,
+            field_type,
+            depth,
+            format,
+        ),
+        _ => println!(
+            "{}{}: {}",
+            indent,
+            name,
+            value_string(value, path, field_type, format)
+        ),
+    }
+}
 
-                            // Format the datetime how you want
-                            let newdate = datetime.format("%Y-%m-%d %H:%M:%S");
+/// Fetches a document and its stored cbor through the primary key.
+pub fn document_for_id(
+    drive: &Drive,
+    contract: &Contract,
+    document_type: &DocumentType,
+    id: &[u8; 32],
+) -> Result<Option<(Document, Vec<u8>)>, Error> {
+    let query = DriveQuery {
+        contract,
+        document_type,
+        internal_clauses: InternalClauses {
+            primary_key_equal_clause: Some(WhereClause {
+                field: "$id".to_string(),
+                operator: WhereOperator::Equal,
+                value: Value::Bytes(id.to_vec()),
+            }),
+            ..Default::default()
+        },
+        offset: 0,
+        limit: 1,
+        order_by: IndexMap::new(),
+        start_at: None,
+        start_at_included: false,
+        block_time: None,
+    };
+    let (results, _, _) = query.execute_no_proof(drive, None)?;
+    match results.into_iter().next() {
+        None => Ok(None),
+        Some(document_cbor) => {
+            let document = Document::from_cbor(document_cbor.as_slice(), None, None)?;
+            Ok(Some((document, document_cbor)))
+        }
+    }
+}
 
-                            format!("{}", newdate)
-                        }
-                    }
-                }
-                _ => {
-                    format!("{}", float)
+fn prompt_get(input: String, drive: &Drive, contract: &Contract, format: ValueFormat) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 3 {
+        println!("### ERROR! Two parameters should be provided");
+        return;
+    }
+    let document_type_name = args.get(1).unwrap();
+    let document_type = match contract.document_type_for_name(document_type_name) {
+        Ok(document_type) => document_type,
+        Err(_) => {
+            println!("### ERROR! Document type does not exist");
+            return;
+        }
+    };
+    let id = match decode_id(args.get(2).unwrap()) {
+        Some(id) => id,
+        None => {
+            println!("### ERROR! Could not decode id");
+            return;
+        }
+    };
+    let (document, document_cbor) = match document_for_id(drive, contract, document_type, &id) {
+        Ok(Some(document_and_cbor)) => document_and_cbor,
+        Ok(None) => {
+            println!("### ERROR! No {} document with that id", document_type_name);
+            return;
+        }
+        Err(e) => {
+            println!("### ERROR! Could not execute query");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    let epoch = grove::document_element(drive, contract, document_type_name, &id)
+        .ok()
+        .and_then(|element| grove::element_storage_flags(&element))
+        .map_or("unknown".to_string(), |storage_flags| {
+            format!("{}", storage_flags.epoch)
+        });
+
+    println!("$id: {}", bs58::encode(document.id).into_string());
+    println!("$ownerId: {}", bs58::encode(document.owner_id).into_string());
+    println!("cbor size: {} bytes", document_cbor.len());
+    println!("storage epoch: {}", epoch);
+    for (key, value) in document.properties.iter() {
+        let field_type = document_type
+            .properties
+            .get(key)
+            .map(|document_field| &document_field.document_type);
+        let path = format!("{}.{}", document_type_name, key);
+        print_full_value(key.as_str(), path.as_str(), value, field_type, 0, format);
+    }
+}
+
+/// Builds the Drive query of a select at the session time, dates written as text in the where
+/// clause are read in the display timezone.
+fn drive_query<'a>(
+    select: &Select,
+    contract: &'a Contract,
+    session: &Session,
+) -> Option<DriveQuery<'a>> {
+    let drive_sql = match contract.document_type_for_name(select.document_type_name.as_str()) {
+        Ok(document_type) => {
+            let now = session.now();
+            let timezone = session.display.timezone;
+            let resolve = |text: &str| {
+                parse_date(text, now, timezone)
+                    .map(|datetime| timestamp_seconds(datetime).to_string())
+            };
+            match resolve_literals(
+                select.drive_sql.as_str(),
+                &date_columns(document_type),
+                &resolve,
+            ) {
+                Ok(drive_sql) => drive_sql,
+                Err(e) => {
+                    println!("### ERROR! Invalid date");
+                    println!("### Info {}", e);
+                    return None;
                 }
             }
         }
-        Value::Text(text) => {
-            let len = text.len();
-            if len > 20 {
-                let first_text = text.split_at(20).0.to_string();
-                format!("{}[...({})]", first_text, len)
-            } else {
-                text.clone()
+        Err(_) => select.drive_sql.clone(),
+    };
+    match DriveQuery::from_sql_expr(drive_sql.as_str(), contract) {
+        Ok(query) => Some(DriveQuery {
+            block_time: session.block_time(),
+            ..query
+        }),
+        Err(e) => {
+            println!("### ERROR! Invalid query");
+            println!("### Info {:?}", e);
+            None
+        }
+    }
+}
+
+/// Runs a select on the contract in use, or on another loaded contract when the table is
+/// written as `alias.documentType`.
+fn prompt_query(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &ContractRegistry,
+    session: &Session,
+    format: ValueFormat,
+) -> Option<(DocumentType, Vec<Document>)> {
+    let select = match parse_select(input.as_str()) {
+        Ok(select) => select,
+        Err(e) => {
+            println!("### ERROR! Invalid query");
+            println!("### Info {}", e);
+            return None;
+        }
+    };
+    let (contract, format) = match &select.contract_alias {
+        None => (contract, format),
+        Some(alias) => match contracts.get(alias.as_str()) {
+            Some(loaded) => (
+                loaded.contract.as_ref(),
+                ValueFormat {
+                    media_types: Some(&loaded.media_types),
+                    ..format
+                },
+            ),
+            None => {
+                println!("### ERROR! No contract is loaded as {}", alias);
+                return None;
+            }
+        },
+    };
+    let query = drive_query(&select, contract, session)?;
+    let document_type = query.document_type;
+    let columns = match &select.projection {
+        Projection::Columns(columns) => {
+            if let Err(e) = check_columns(document_type, columns) {
+                println!("### ERROR! {}", e);
+                return None;
+            }
+            columns.clone()
+        }
+        Projection::Aggregates { columns, group_by } => {
+            let aggregated_columns = columns.iter().filter_map(|column| match column {
+                SelectColumn::Aggregate(_, column) => column.clone(),
+                SelectColumn::Column(_) => None,
+            });
+            let checked_columns: Vec<String> =
+                group_by.iter().cloned().chain(aggregated_columns).collect();
+            if let Err(e) = check_columns(document_type, &checked_columns) {
+                println!("### ERROR! {}", e);
+                return None;
             }
+            vec![]
         }
-        Value::Bool(b) => {
-            format!("{}", b)
+        _ => result_columns(document_type, format),
+    };
+    let results = match select.projection {
+        Projection::Aggregates { .. } if !select.has_limit => execute_paged(drive, query),
+        _ => execute_documents(drive, query),
+    };
+    match results {
+        Ok((documents, processing_fee)) => {
+            println!("processing fee is {}", processing_fee);
+            if let Projection::Aggregates { columns, group_by } = &select.projection {
+                print_aggregates(document_type, &documents, columns, group_by, format);
+                return None;
+            }
+            print_results(document_type, &documents, &columns, format);
+            Some((document_type.clone(), documents))
+        }
+        Err(e) => {
+            println!("invalid query, try again");
+            println!("### Info {:?}", e);
+            None
         }
-        Value::Null => "None".to_string(),
-        Value::Tag(_, _) => "Tag".to_string(),
-        Value::Array(_) => "Array".to_string(),
-        Value::Map(_) => "Map".to_string(),
-        _ => "".to_string(),
     }
 }
 
-fn table_for_document_type(document_type: &DocumentType) -> Table {
-    let mut cells: Vec<Cell> = vec![Cell::new("$id"), Cell::new("$owner")];
-    for (key, field_type) in document_type.properties.iter() {
-        cells.push(Cell::new(key.as_str()));
+pub fn print_worst_case_fees(drive: &Drive, contract: &Contract, document_type_name: &str) {
+    match drive.worst_case_fee_for_document_type_with_name(contract, document_type_name) {
+        Ok((storage_fee, processing_fee)) => {
+            println!(
+                "Worst case storage fee: {} ({:.2}¢)",
+                storage_fee,
+                credits_in_cents(storage_fee as f64)
+            );
+            println!(
+                "Worst case processing fee: {} ({:.2}¢)",
+                processing_fee,
+                credits_in_cents(processing_fee as f64)
+            );
+        }
+        Err(e) => {
+            println!("### ERROR! Could not get worst case fee from contract");
+            println!("### Info {:?}", e);
+        }
     }
+}
 
-    let mut table = Table::new();
-    table.add_row(Row::new(cells));
-    table
+fn prompt_cost(input: String, drive: &Drive, contract: &Contract) {
+    let args = input.split_whitespace();
+    if args.count() != 2 {
+        println!("### ERROR! Two parameter should be provided");
+    } else {
+        let document_type_name = input.split_whitespace().last().unwrap();
+        let document_type_result = contract.document_type_for_name(document_type_name);
+        match document_type_result {
+            Ok(_) => {
+                println!("For {} document type:", document_type_name);
+                print_worst_case_fees(drive, contract, document_type_name);
+            }
+            Err(_) => {
+                println!("### ERROR! Document type does not exist");
+            }
+        }
+    }
 }
 
-fn print_results(document_type: &DocumentType, documents: Vec<Document>) {
-    let mut table = table_for_document_type(document_type);
-    for document in documents.iter() {
-        let mut cells: Vec<Cell> = vec![
-            Cell::new(bs58::encode(document.id.as_slice()).into_string().as_str()),
-            Cell::new(
-                bs58::encode(document.owner_id.as_slice())
-                    .into_string()
-                    .as_str(),
-            ),
-        ];
-        for (key, value) in document.properties.iter() {
-            let document_field = document_type.properties.get(key).unwrap();
-            cells.push(Cell::new(
-                reduced_value_string_representation(value, &document_field.document_type).as_str(),
-            ));
+/// Columns of a result table, the ones chosen with `columns` or the system ids and every
+/// property.
+fn result_columns(document_type: &DocumentType, format: ValueFormat) -> Vec<String> {
+    match format.settings.columns.get(&document_type.name) {
+        Some(columns) => columns.clone(),
+        None => ["$id", "$ownerId"]
+            .iter()
+            .map(|column| column.to_string())
+            .chain(document_type.properties.keys().cloned())
+            .collect(),
+    }
+}
+
+/// Renders the value of a result column, ids in base58 and missing values as the null marker.
+pub fn column_string(
+    document_type: &DocumentType,
+    column: &str,
+    value: Option<&Value>,
+    format: ValueFormat,
+) -> String {
+    match (column, value) {
+        (_, None) => NULL_MARKER.to_string(),
+        ("$id" | "$ownerId", Some(Value::Bytes(id))) => bs58::encode(id).into_string(),
+        (_, Some(value)) => {
+            let field_type = match column {
+                "$createdAt" | "$updatedAt" => Some(&DocumentFieldType::Date),
+                _ => document_type
+                    .properties
+                    .get(column)
+                    .map(|document_field| &document_field.document_type),
+            };
+            let path = format!("{}.{}", document_type.name, column);
+            value_string(value, path.as_str(), field_type, format)
         }
+    }
+}
+
+fn result_cell(
+    document_type: &DocumentType,
+    document: &Document,
+    column: &str,
+    format: ValueFormat,
+) -> String {
+    let value = document_value(document, column);
+    column_string(document_type, column, value.as_ref(), format)
+}
+
+/// Prints documents with one column per property, documents missing a property get the null
+/// marker so the following values stay under their header. Rows are numbered for `show`.
+fn print_results(
+    document_type: &DocumentType,
+    documents: &[Document],
+    columns: &[String],
+    format: ValueFormat,
+) {
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        std::iter::once(Cell::new("#"))
+            .chain(columns.iter().map(|column| Cell::new(column.as_str())))
+            .collect(),
+    ));
+    for (i, document) in documents.iter().enumerate() {
+        let cells: Vec<Cell> = std::iter::once(Cell::new((i + 1).to_string().as_str()))
+            .chain(columns.iter().map(|column| {
+                Cell::new(result_cell(document_type, document, column.as_str(), format).as_str())
+            }))
+            .collect();
         table.add_row(Row::new(cells));
     }
 
@@ -673,7 +1883,9 @@ fn all(
     drive: &Drive,
     contract: &Contract,
     document_type_name: &str,
-) {
+    block_time: Option<f64>,
+    format: ValueFormat,
+) -> (DocumentType, Vec<Document>) {
     let order_by: IndexMap<String, OrderClause> = order_by_strings
         .iter()
         .map(|field| {
@@ -699,7 +1911,7 @@ fn all(
         order_by,
         start_at: None,
         start_at_included: false,
-        block_time: None,
+        block_time,
     };
     let (results, _, processing_fee) = query
         .execute_no_proof(&drive, None)
@@ -713,16 +1925,26 @@ fn all(
         })
         .collect();
     println!("processing fee is {}", processing_fee);
-    print_results(&document_type, documents);
+    let columns = result_columns(document_type, format);
+    print_results(document_type, &documents, &columns, format);
+    (document_type.clone(), documents)
 }
 
-fn prompt_all(input: String, drive: &Drive, contract: &Contract) {
+fn prompt_all(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    session: &Session,
+    format: ValueFormat,
+) -> Option<(DocumentType, Vec<Document>)> {
     let args = input.split_whitespace();
     let count = args.count();
     if count > 4 {
         println!("### ERROR! At max three parameters should be provided");
+        None
     } else if count < 2 {
         println!("### ERROR! At least one parameter for the document type name should be provided");
+        None
     } else {
         let split: Vec<String> = input.split_whitespace().map(|s| s.to_string()).collect();
         let document_type_name = split.get(1).unwrap();
@@ -764,47 +1986,257 @@ fn prompt_all(input: String, drive: &Drive, contract: &Contract) {
             chars.next_back();
             order_by = chars.as_str().split(',').map(|s| s.to_string()).collect();
         }
-        all(order_by, limit, drive, contract, document_type_name);
+        if contract.document_type_for_name(document_type_name).is_err() {
+            println!("### ERROR! Document type does not exist");
+            return None;
+        }
+        Some(all(
+            order_by,
+            limit,
+            drive,
+            contract,
+            document_type_name,
+            session.block_time(),
+            format,
+        ))
+    }
+}
+
+/// Expands one row of the last result table with every value in full.
+fn prompt_show(input: String, session: &Session, format: ValueFormat) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 {
+        println!("### ERROR! A row number should be provided");
+        return;
+    }
+    let (document_type, documents) = match &session.last_results {
+        Some(last_results) => last_results,
+        None => {
+            println!("### ERROR! There are no results yet, use all or select first");
+            return;
+        }
+    };
+    let document = match args.get(1).unwrap().parse::<usize>() {
+        Ok(row) if row >= 1 && row <= documents.len() => &documents[row - 1],
+        _ => {
+            println!("### ERROR! The row should be between 1 and {}", documents.len());
+            return;
+        }
+    };
+    let format = ValueFormat {
+        full: true,
+        ..format
+    };
+    println!("$id: {}", bs58::encode(document.id).into_string());
+    println!("$ownerId: {}", bs58::encode(document.owner_id).into_string());
+    for (key, value) in document.properties.iter() {
+        let field_type = document_type
+            .properties
+            .get(key)
+            .map(|document_field| &document_field.document_type);
+        let path = format!("{}.{}", document_type.name, key);
+        print_full_value(key.as_str(), path.as_str(), value, field_type, 0, format);
+    }
+}
+
+/// Rendering settings of the session for the contract in use.
+fn value_format<'a>(session: &'a Session, contracts: &'a ContractRegistry) -> ValueFormat<'a> {
+    ValueFormat {
+        settings: &session.display,
+        media_types: session
+            .current_contract
+            .as_deref()
+            .and_then(|alias| contracts.get(alias))
+            .map(|loaded| &loaded.media_types),
+        full: false,
+    }
+}
+
+/// System timestamps declared by the document types of the contract in use.
+fn timestamp_fields<'a>(
+    session: &Session,
+    contracts: &'a ContractRegistry,
+) -> Option<&'a BTreeMap<String, Vec<String>>> {
+    session
+        .current_contract
+        .as_deref()
+        .and_then(|alias| contracts.get(alias))
+        .map(|loaded| &loaded.timestamp_fields)
+}
+
+/// File the contract in use was loaded from.
+fn contract_path<'a>(session: &Session, contracts: &'a ContractRegistry) -> Option<&'a str> {
+    session
+        .current_contract
+        .as_deref()
+        .and_then(|alias| contracts.get(alias))
+        .map(|loaded| loaded.path.as_str())
+}
+
+pub fn contract_command(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
+    let mutating = is_mutating_command(input.as_str());
+    let result = if input == "view" || input.starts_with("view ") || input == "v" {
+        prompt_view(input, &drive, contract, contract_path(session, contracts));
+        true
+    } else if input.starts_with("pop ") {
+        prompt_populate(input, &drive, contract, session);
+        true
+    } else if input.starts_with("popfull ") || input.starts_with("pf ") {
+        prompt_populate_full(input, &drive, contract, session);
+        true
+    } else if input.starts_with("benchpop ") || input.starts_with("bp ") {
+        prompt_bench(input, &drive, contract, session);
+        true
+    } else if input.starts_with("all") {
+        let (input, full) = take_flag(input, "--full");
+        let format = ValueFormat {
+            full,
+            ..value_format(session, contracts)
+        };
+        if let Some(results) = prompt_all(input, &drive, &contract, session, format) {
+            session.last_results = Some(results);
+        }
+        true
+    } else if input.starts_with("insert ") || input.starts_with("i ") {
+        let timestamp_fields = timestamp_fields(session, contracts);
+        prompt_insert(input, &drive, &contract, session, timestamp_fields);
+        true
+    } else if input.starts_with("update ") {
+        prompt_update(
+            input,
+            &drive,
+            &contract,
+            session,
+            timestamp_fields(session, contracts),
+        );
+        true
+    } else if input.starts_with("delete from ") {
+        prompt_delete_where(
+            input,
+            &drive,
+            &contract,
+            session,
+            value_format(session, contracts),
+            rl,
+        );
+        true
+    } else if input.starts_with("delete ") {
+        prompt_delete(input, &drive, &contract, session);
+        true
+    } else if input == "whoami" || input.starts_with("whoami ") {
+        prompt_whoami(input, session);
+        true
+    } else if input.starts_with("identity ") {
+        prompt_identity(input, session);
+        true
+    } else if input == "clock" || input.starts_with("clock ") {
+        prompt_clock(input, session);
+        true
+    } else if input == "seed" || input.starts_with("seed ") {
+        prompt_seed(input, session);
+        true
+    } else if input == "epochs" {
+        grove::print_epochs(&drive, &contract);
+        true
+    } else if input == "epoch" || input.starts_with("epoch ") {
+        prompt_epoch(input, session);
+        true
+    } else if input == "tree" || input.starts_with("tree ") {
+        grove::prompt_tree(input, &drive, &contract, session);
+        true
+    } else if input == "roothash" || input.starts_with("roothash ") {
+        grove::prompt_root_hash(input, &drive, session);
+        true
+    } else if input.starts_with("fingerprint ") {
+        grove::prompt_fingerprint(input, &drive, &contract);
+        true
+    } else if input == "size" || input.starts_with("size ") {
+        grove::prompt_size(input, &drive, &contract);
+        true
+    } else if input.starts_with("save ") {
+        whatif::prompt_save_query(input, session);
+        true
+    } else if input == "queries" {
+        whatif::print_saved_queries(session);
+        true
+    } else if input.starts_with("whatif ") {
+        whatif::prompt_whatif(input, &drive, &contract, session);
+        true
+    } else if input.starts_with("mine ") {
+        if let Some(results) =
+            prompt_mine(input, &drive, &contract, session, value_format(session, contracts))
+        {
+            session.last_results = Some(results);
+        }
+        true
+    } else if input.starts_with("get ") {
+        prompt_get(input, &drive, &contract, value_format(session, contracts));
+        true
+    } else if input.starts_with("checkunique ") {
+        prompt_check_unique(input, &drive, &contract);
+        true
+    } else if input.starts_with("select ") {
+        let (input, full) = take_flag(input, "--full");
+        let format = ValueFormat {
+            full,
+            ..value_format(session, contracts)
+        };
+        if let Some(results) = prompt_query(input, &drive, &contract, contracts, session, format) {
+            session.last_results = Some(results);
+        }
+        true
+    } else if input.starts_with("show ") {
+        prompt_show(input, session, value_format(session, contracts));
+        true
+    } else if input == "display" || input.starts_with("display ") {
+        display::prompt_display(input, session);
+        true
+    } else if input.starts_with("columns ") {
+        display::prompt_columns(input, &contract, session);
+        true
+    } else if input.starts_with("use ") {
+        prompt_use(input, contracts, session);
+        true
+    } else if input == "contracts" {
+        print_contracts(drive, contracts, session);
+        true
+    } else if input.starts_with("diffcontract ") {
+        prompt_diff_contract(input);
+        true
+    } else if input.starts_with("upgrade ") {
+        prompt_upgrade(input, &drive, &contract, contracts, session, rl);
+        true
+    } else if input.starts_with("cost ") {
+        prompt_cost(input, &drive, &contract);
+        true
+    } else if input == "exit" {
+        false
+    } else {
+        true
+    };
+    if mutating {
+        grove::print_root_hash_if_enabled(drive, session);
     }
+    result
 }
 
-fn contract_rl(drive: &Drive, contract: &Contract, rl: &mut Editor<()>) -> bool {
+fn contract_rl(
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
     let readline = rl.readline("> ");
     match readline {
-        Ok(input) => {
-            if input.starts_with("view ") || input == "v" {
-                print_contract_format(contract);
-                true
-            } else if input.starts_with("pop ") {
-                prompt_populate(input, &drive, contract);
-                true
-            } else if input.starts_with("popfull ") || input.starts_with("pf ") {
-                prompt_populate_full(input, &drive, contract);
-                true
-            } else if input.starts_with("benchpop ") || input.starts_with("bp ") {
-                prompt_bench(input, &drive, contract);
-                true
-            } else if input.starts_with("all") {
-                prompt_all(input, &drive, &contract);
-                true
-            } else if input.starts_with("insert ") || input.starts_with("i ") {
-                prompt_insert(input, &drive, &contract);
-                true
-            } else if input.starts_with("delete ") {
-                prompt_delete(input, &drive, &contract);
-                true
-            } else if input.starts_with("select ") {
-                prompt_query(input, &drive, &contract);
-                true
-            } else if input.starts_with("cost ") {
-                prompt_cost(input, &drive, &contract);
-                true
-            } else if input == "exit" {
-                false
-            } else {
-                true
-            }
-        }
+        Ok(input) => contract_command(input, drive, contract, contracts, session, rl),
         Err(_) => {
             println!("no input, try again");
             true
@@ -812,7 +2244,40 @@ fn contract_rl(drive: &Drive, contract: &Contract, rl: &mut Editor<()>) -> bool
     }
 }
 
-pub fn contract_loop(drive: &Drive, contract: &Contract, rl: &mut Editor<()>) -> bool {
+pub fn contract_loop(
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
     print_contract_options(&contract);
-    contract_rl(drive, contract, rl)
+    contract_rl(drive, contract, contracts, session, rl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_base58_hex_and_base64_ids() {
+        let id = [0xab_u8; 32];
+        assert_eq!(decode_id(bs58::encode(id).into_string().as_str()), Some(id));
+        assert_eq!(decode_id(hex::encode(id).as_str()), Some(id));
+        assert_eq!(decode_id(base64::encode(id).as_str()), Some(id));
+
+        let id: [u8; 32] = core::array::from_fn(|i| i as u8);
+        assert_eq!(decode_id(bs58::encode(id).into_string().as_str()), Some(id));
+        assert_eq!(decode_id(hex::encode(id).as_str()), Some(id));
+        assert_eq!(decode_id(base64::encode(id).as_str()), Some(id));
+    }
+
+    #[test]
+    fn rejects_invalid_ids() {
+        assert_eq!(decode_id(""), None);
+        assert_eq!(decode_id("not an id"), None);
+        assert_eq!(decode_id(bs58::encode([1_u8; 31]).into_string().as_str()), None);
+        assert_eq!(decode_id(hex::encode([1_u8; 33]).as_str()), None);
+        assert_eq!(decode_id(base64::encode([1_u8; 16]).as_str()), None);
+    }
 }
@@ -0,0 +1,326 @@
+use crate::contract::{
+    contract_command, populate_with_documents, print_contract_options, print_fees,
+};
+use crate::date::millis_value;
+use crate::grove;
+use crate::registry::ContractRegistry;
+use crate::session::Session;
+use ciborium::value::{Integer as cborInteger, Value};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_distr::{Distribution, Pareto};
+use rs_drive::common;
+use rs_drive::contract::{document::Document, Contract};
+use rs_drive::drive::Drive;
+use rs_drive::error::Error;
+use rustyline::Editor;
+use std::collections::{BTreeMap, HashSet};
+use std::time::SystemTime;
+
+const MILLIS_IN_A_YEAR: i64 = 31_536_000_000;
+
+/// Chance that a user answers a contact request with one of their own, making the contact
+/// established.
+const RECIPROCATION_PROBABILITY: f64 = 0.7;
+
+const PUBLIC_MESSAGES: [&str; 6] = [
+    "Hey there! I am using Dash",
+    "Pay me in Dash",
+    "Available",
+    "Busy, send me a message",
+    "Building on Dash Platform",
+    "",
+];
+
+struct ContactGraph {
+    identities: Vec<[u8; 32]>,
+    /// Contact requests as (sender, recipient) indexes into the identities
+    requests: Vec<(usize, usize)>,
+}
+
+impl ContactGraph {
+    /// Builds a graph where out degrees follow a heavy tailed distribution averaging
+    /// `avg_contacts` and recipients are chosen by preferential attachment, so that a few
+    /// popular users get most requests like on real social networks.
+    fn random(users: u32, avg_contacts: u32, rng: &mut StdRng) -> Self {
+        let users = users as usize;
+        let identities: Vec<[u8; 32]> = (0..users).map(|_| rng.gen::<[u8; 32]>()).collect();
+        let mut requests = vec![];
+        if users < 2 || avg_contacts == 0 {
+            return ContactGraph {
+                identities,
+                requests,
+            };
+        }
+
+        // a Pareto distribution with a shape of 2 has a mean of twice its scale
+        let out_degree_distribution = Pareto::new(avg_contacts as f64 / 2.0, 2.0)
+            .expect("expected a valid pareto distribution");
+        // each user appears once plus once per request received, sampling uniformly from this
+        // list is proportional to the in degree
+        let mut attachment: Vec<usize> = (0..users).collect();
+        let mut pairs: HashSet<(usize, usize)> = HashSet::new();
+
+        for sender in 0..users {
+            let out_degree = (out_degree_distribution.sample(rng).round() as usize).min(users - 1);
+            let mut attempts = 0;
+            let mut sent = 0;
+            while sent < out_degree && attempts < out_degree * 10 {
+                attempts += 1;
+                let recipient = *attachment.choose(rng).unwrap();
+                if recipient == sender || !pairs.insert((sender, recipient)) {
+                    continue;
+                }
+                requests.push((sender, recipient));
+                attachment.push(recipient);
+                sent += 1;
+                if rng.gen_bool(RECIPROCATION_PROBABILITY) && pairs.insert((recipient, sender)) {
+                    requests.push((recipient, sender));
+                    attachment.push(sender);
+                }
+            }
+        }
+        ContactGraph {
+            identities,
+            requests,
+        }
+    }
+
+    fn print_summary(&self) {
+        let users = self.identities.len();
+        let mut out_degrees = vec![0usize; users];
+        let mut in_degrees = vec![0usize; users];
+        let pairs: HashSet<(usize, usize)> = self.requests.iter().copied().collect();
+        for (sender, recipient) in self.requests.iter() {
+            out_degrees[*sender] += 1;
+            in_degrees[*recipient] += 1;
+        }
+        let established = self
+            .requests
+            .iter()
+            .filter(|(sender, recipient)| sender < recipient && pairs.contains(&(*recipient, *sender)))
+            .count();
+        println!("Identities: {}", users);
+        println!("Contact requests: {}", self.requests.len());
+        println!("Established contacts: {}", established);
+        println!(
+            "Max requests sent: {}, max requests received: {}",
+            out_degrees.iter().max().unwrap_or(&0),
+            in_degrees.iter().max().unwrap_or(&0)
+        );
+    }
+}
+
+fn integer_value(integer: u32) -> Value {
+    Value::Integer(cborInteger::from(integer))
+}
+
+fn random_bytes(len: usize, rng: &mut StdRng) -> Value {
+    Value::Bytes((0..len).map(|_| rng.gen::<u8>()).collect())
+}
+
+fn random_past_time(now: i64, rng: &mut StdRng) -> i64 {
+    now - rng.gen_range(0..MILLIS_IN_A_YEAR)
+}
+
+fn profile_documents(identities: &[[u8; 32]], now: i64, rng: &mut StdRng) -> Vec<Document> {
+    let first_names =
+        common::text_file_strings("src/supporting_files/contract/family/first-names.txt");
+    let last_names =
+        common::text_file_strings("src/supporting_files/contract/family/last-names.txt");
+    identities
+        .iter()
+        .map(|identity| {
+            let first_name = first_names.choose(rng).unwrap();
+            let last_name = last_names.choose(rng).unwrap();
+            let mut display_name = format!("{} {}", first_name, last_name);
+            display_name.truncate(25);
+            let created_at = random_past_time(now, rng);
+            let mut properties = BTreeMap::new();
+            properties.insert("displayName".to_string(), Value::Text(display_name));
+            properties.insert(
+                "publicMessage".to_string(),
+                Value::Text(PUBLIC_MESSAGES.choose(rng).unwrap().to_string()),
+            );
+            properties.insert(
+                "avatarUrl".to_string(),
+                Value::Text(format!(
+                    "https://avatars.example.com/{}.png",
+                    bs58::encode(identity).into_string()
+                )),
+            );
+            properties.insert("$createdAt".to_string(), millis_value(created_at));
+            properties.insert(
+                "$updatedAt".to_string(),
+                millis_value(rng.gen_range(created_at..=now)),
+            );
+            Document {
+                id: rng.gen::<[u8; 32]>(),
+                properties,
+                owner_id: *identity,
+            }
+        })
+        .collect()
+}
+
+fn contact_request_documents(graph: &ContactGraph, now: i64, rng: &mut StdRng) -> Vec<Document> {
+    graph
+        .requests
+        .iter()
+        .map(|(sender, recipient)| {
+            let mut properties = BTreeMap::new();
+            properties.insert(
+                "toUserId".to_string(),
+                Value::Bytes(graph.identities[*recipient].to_vec()),
+            );
+            properties.insert("encryptedPublicKey".to_string(), random_bytes(96, rng));
+            properties.insert("senderKeyIndex".to_string(), integer_value(rng.gen_range(0..4)));
+            properties.insert(
+                "recipientKeyIndex".to_string(),
+                integer_value(rng.gen_range(0..4)),
+            );
+            properties.insert("accountReference".to_string(), integer_value(rng.gen()));
+            if rng.gen_bool(0.5) {
+                properties.insert("encryptedAccountLabel".to_string(), random_bytes(48, rng));
+            }
+            properties.insert(
+                "$createdAt".to_string(),
+                millis_value(random_past_time(now, rng)),
+            );
+            Document {
+                id: rng.gen::<[u8; 32]>(),
+                properties,
+                owner_id: graph.identities[*sender],
+            }
+        })
+        .collect()
+}
+
+/// Creates `users` identities, a profile for each of them and a contact graph between them
+/// made of contactRequest documents whose `toUserId` are existing identities.
+pub fn populate_graph(
+    users: u32,
+    avg_contacts: u32,
+    drive: &Drive,
+    contract: &Contract,
+    session: &mut Session,
+) -> Result<(i64, u64, u32), Error> {
+    let mut rng = session.rng();
+    let now = session.now().timestamp_millis();
+
+    let graph = ContactGraph::random(users, avg_contacts, &mut rng);
+    graph.print_summary();
+
+    let profile_document_type = contract.document_type_for_name("profile")?;
+    let profiles = profile_documents(&graph.identities, now, &mut rng);
+    let (profile_storage_fee, profile_processing_fee) = populate_with_documents(
+        profiles,
+        drive,
+        profile_document_type,
+        contract,
+        true,
+        session,
+    )?;
+
+    let contact_request_document_type = contract.document_type_for_name("contactRequest")?;
+    let contact_requests = contact_request_documents(&graph, now, &mut rng);
+    let document_count = users + contact_requests.len() as u32;
+    let (request_storage_fee, request_processing_fee) = populate_with_documents(
+        contact_requests,
+        drive,
+        contact_request_document_type,
+        contract,
+        true,
+        session,
+    )?;
+
+    Ok((
+        profile_storage_fee + request_storage_fee,
+        profile_processing_fee + request_processing_fee,
+        document_count,
+    ))
+}
+
+fn print_dashpay_options(contract: &Contract) {
+    print_contract_options(contract);
+    println!("### dashpay-graph / dg <users> <avg_contacts>                     - populate identities with profiles and a contact request graph");
+    println!();
+}
+
+fn prompt_populate_graph(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    session: &mut Session,
+) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 3 {
+        println!("### ERROR! Two parameters should be provided");
+    } else {
+        let users_str = args.get(1).unwrap();
+        let avg_contacts_str = args.get(2).unwrap();
+        match (users_str.parse::<u32>(), avg_contacts_str.parse::<u32>()) {
+            (Ok(users), Ok(avg_contacts)) => {
+                if users == 0 || users > 10000 {
+                    println!("### ERROR! Users must be between 1 and 10000");
+                } else if avg_contacts >= users {
+                    println!("### ERROR! The average contacts must be lower than the users");
+                } else {
+                    let start_time = SystemTime::now();
+                    match populate_graph(users, avg_contacts, drive, contract, session) {
+                        Ok((storage_fee, processing_fee, document_count)) => {
+                            if let Ok(n) = SystemTime::now().duration_since(start_time) {
+                                print_fees(storage_fee, processing_fee, document_count);
+                                println!("Time taken: {}", n.as_secs_f64());
+                            }
+                        }
+                        Err(e) => {
+                            println!("### ERROR! Could not populate the contact graph");
+                            println!("### Info {:?}", e);
+                        }
+                    }
+                }
+            }
+            _ => {
+                println!("### ERROR! Integers were not provided for the users and contacts");
+            }
+        }
+    }
+}
+
+fn dashpay_rl(
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
+    let readline = rl.readline("> ");
+    match readline {
+        Ok(input) => {
+            if input.starts_with("dashpay-graph ") || input.starts_with("dg ") {
+                prompt_populate_graph(input, drive, contract, session);
+                grove::print_root_hash_if_enabled(drive, session);
+                true
+            } else {
+                contract_command(input, drive, contract, contracts, session, rl)
+            }
+        }
+        Err(_) => {
+            println!("no input, try again");
+            true
+        }
+    }
+}
+
+pub fn dashpay_loop(
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
+    print_dashpay_options(contract);
+    dashpay_rl(drive, contract, contracts, session, rl)
+}
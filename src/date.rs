@@ -0,0 +1,339 @@
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use ciborium::value::Value;
+use rs_drive::contract::types::{DocumentField, DocumentFieldType};
+use rs_drive::contract::DocumentType;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// System fields holding timestamps.
+pub const SYSTEM_DATE_FIELDS: [&str; 2] = ["$createdAt", "$updatedAt"];
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f %:z";
+
+/// Timezone dates are shown in, and naive dates without an offset are read in.
+#[derive(Clone, Copy)]
+pub enum DisplayTimezone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl DisplayTimezone {
+    /// Reads `utc`, `local` or an offset like `+02:00`, `-0530` or `+2`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "utc" => return Some(DisplayTimezone::Utc),
+            "local" => return Some(DisplayTimezone::Local),
+            _ => {}
+        }
+        let (sign, offset) = match (name.strip_prefix('+'), name.strip_prefix('-')) {
+            (Some(offset), _) => (1, offset),
+            (_, Some(offset)) => (-1, offset),
+            _ => return None,
+        };
+        let (hours, minutes) = match offset.split_once(':') {
+            Some(split) => split,
+            None if offset.len() == 4 && offset.is_ascii() => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+        FixedOffset::east_opt(sign * seconds).map(DisplayTimezone::Fixed)
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            DisplayTimezone::Utc => "utc".to_string(),
+            DisplayTimezone::Local => "local".to_string(),
+            DisplayTimezone::Fixed(offset) => offset.to_string(),
+        }
+    }
+
+    fn utc_from_local(&self, naive: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            DisplayTimezone::Utc => Some(DateTime::from_utc(*naive, Utc)),
+            DisplayTimezone::Local => Local
+                .from_local_datetime(naive)
+                .single()
+                .map(|datetime| datetime.with_timezone(&Utc)),
+            DisplayTimezone::Fixed(offset) => offset
+                .from_local_datetime(naive)
+                .single()
+                .map(|datetime| datetime.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Reads a duration like `30s`, `15m`, `1h`, `2d` or `1w`.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let unit_start = text.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = text.split_at(unit_start);
+    let unit_millis = match unit {
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 604_800_000,
+        _ => return None,
+    };
+    let millis = amount.parse::<i64>().ok()?.checked_mul(unit_millis)?;
+    Some(Duration::milliseconds(millis))
+}
+
+/// Reads an ISO-8601 date, or `now` and a duration before or after it like `-2d` or `+1h`.
+/// Dates without an offset are in the display timezone.
+pub fn parse_date(
+    text: &str,
+    now: DateTime<Utc>,
+    timezone: DisplayTimezone,
+) -> Result<DateTime<Utc>, String> {
+    let relative = match (text.strip_prefix('-'), text.strip_prefix('+')) {
+        _ if text == "now" => Some(now),
+        (Some(duration), _) => parse_duration(duration).and_then(|d| now.checked_sub_signed(d)),
+        (_, Some(duration)) => parse_duration(duration).and_then(|d| now.checked_add_signed(d)),
+        _ => None,
+    };
+    if let Some(datetime) = relative {
+        return Ok(datetime);
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.and_hms(0, 0, 0))
+        })
+        .and_then(|naive| timezone.utc_from_local(&naive))
+        .ok_or(format!(
+            "{} is not an ISO-8601 date or a relative one like now or -2d",
+            text
+        ))
+}
+
+/// Seconds since the epoch with millisecond precision, the way Drive takes the block time.
+pub fn timestamp_seconds(datetime: DateTime<Utc>) -> f64 {
+    datetime.timestamp_millis() as f64 / 1000.0
+}
+
+/// A date value the way Drive stores dates: seconds since the epoch as a float, which is also
+/// what it reads from typed numbers, generates for random documents and compares in queries.
+pub fn timestamp_value(datetime: DateTime<Utc>) -> Value {
+    millis_value(datetime.timestamp_millis())
+}
+
+/// The date value of a time in milliseconds since the epoch.
+pub fn millis_value(millis: i64) -> Value {
+    Value::Float(millis as f64 / 1000.0)
+}
+
+/// Reads a stored date, seconds since the epoch whether written as a float or an integer.
+pub fn timestamp_datetime(value: &Value) -> Option<DateTime<Utc>> {
+    let seconds = match value {
+        Value::Integer(integer) => i64::try_from(i128::from(*integer)).ok()? as f64,
+        Value::Float(float) => *float,
+        _ => return None,
+    };
+    if !seconds.is_finite() || seconds.abs() >= 1e12 {
+        return None;
+    }
+    let millis = (seconds * 1000.0).round() as i64;
+    let naive = NaiveDateTime::from_timestamp_opt(
+        millis.div_euclid(1000),
+        (millis.rem_euclid(1000) * 1_000_000) as u32,
+    )?;
+    Some(DateTime::from_utc(naive, Utc))
+}
+
+pub fn date_string(datetime: DateTime<Utc>, timezone: DisplayTimezone) -> String {
+    match timezone {
+        DisplayTimezone::Utc => datetime.format(DATE_FORMAT).to_string(),
+        DisplayTimezone::Local => datetime
+            .with_timezone(&Local)
+            .format(DATE_FORMAT)
+            .to_string(),
+        DisplayTimezone::Fixed(offset) => datetime
+            .with_timezone(&offset)
+            .format(DATE_FORMAT)
+            .to_string(),
+    }
+}
+
+fn collect_date_columns(
+    prefix: Option<&str>,
+    properties: &BTreeMap<String, DocumentField>,
+    columns: &mut Vec<String>,
+) {
+    for (name, property) in properties.iter() {
+        let column = match prefix {
+            None => name.clone(),
+            Some(prefix) => format!("{}.{}", prefix, name),
+        };
+        match &property.document_type {
+            DocumentFieldType::Date => columns.push(column),
+            DocumentFieldType::Object(properties) => {
+                collect_date_columns(Some(column.as_str()), properties, columns)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// System timestamps and date properties of a document type, nested ones with dotted paths.
+pub fn date_columns(document_type: &DocumentType) -> Vec<String> {
+    let mut columns: Vec<String> = SYSTEM_DATE_FIELDS
+        .iter()
+        .map(|field| field.to_string())
+        .collect();
+    collect_date_columns(None, &document_type.properties, &mut columns);
+    columns
+}
+
+/// Reads the system timestamps each document type of a contract file declares, by listing them
+/// in `required` or indexing them. Drive does not keep them, and only those are set on writes.
+pub fn declared_timestamp_fields(contract_path: &str) -> BTreeMap<String, Vec<String>> {
+    let mut timestamp_fields = BTreeMap::new();
+    let contract = fs::read_to_string(contract_path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<JsonValue>(data.as_str()).ok());
+    if let Some(documents) = contract
+        .as_ref()
+        .and_then(|contract| contract.get("documents"))
+        .and_then(|documents| documents.as_object())
+    {
+        for (name, document_type) in documents.iter() {
+            let required = document_type.get("required").and_then(|r| r.as_array());
+            let indices = document_type.get("indices").and_then(|i| i.as_array());
+            let declared = |field: &str| {
+                required.map_or(false, |required| {
+                    required.iter().any(|name| name.as_str() == Some(field))
+                }) || indices.map_or(false, |indices| {
+                    indices.iter().any(|index| {
+                        index
+                            .get("properties")
+                            .and_then(|properties| properties.as_array())
+                            .map_or(false, |properties| {
+                                properties
+                                    .iter()
+                                    .any(|property| property.get(field).is_some())
+                            })
+                    })
+                })
+            };
+            let fields: Vec<String> = SYSTEM_DATE_FIELDS
+                .iter()
+                .filter(|field| declared(field))
+                .map(|field| field.to_string())
+                .collect();
+            timestamp_fields.insert(name.clone(), fields);
+        }
+    }
+    timestamp_fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2024, 1, 10).and_hms(12, 0, 0)
+    }
+
+    fn offset_seconds(timezone: Option<DisplayTimezone>) -> Option<i32> {
+        match timezone {
+            Some(DisplayTimezone::Fixed(offset)) => Some(offset.local_minus_utc()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30s"), Some(Duration::seconds(30)));
+        assert_eq!(parse_duration("15m"), Some(Duration::minutes(15)));
+        assert_eq!(parse_duration("1h"), Some(Duration::hours(1)));
+        assert_eq!(parse_duration("2d"), Some(Duration::days(2)));
+        assert_eq!(parse_duration("1w"), Some(Duration::weeks(1)));
+        assert_eq!(parse_duration("5"), None);
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("5y"), None);
+        assert_eq!(parse_duration("-5d"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn reads_timezone_names() {
+        assert!(matches!(
+            DisplayTimezone::from_name("utc"),
+            Some(DisplayTimezone::Utc)
+        ));
+        assert!(matches!(
+            DisplayTimezone::from_name("local"),
+            Some(DisplayTimezone::Local)
+        ));
+        assert_eq!(offset_seconds(DisplayTimezone::from_name("+02:00")), Some(7200));
+        assert_eq!(offset_seconds(DisplayTimezone::from_name("+2")), Some(7200));
+        assert_eq!(
+            offset_seconds(DisplayTimezone::from_name("-0530")),
+            Some(-19800)
+        );
+        assert!(DisplayTimezone::from_name("+25:00").is_none());
+        assert!(DisplayTimezone::from_name("europe").is_none());
+        assert_eq!(
+            DisplayTimezone::from_name("+02:00").unwrap().name(),
+            "+02:00"
+        );
+    }
+
+    #[test]
+    fn parses_relative_dates() {
+        let timezone = DisplayTimezone::Utc;
+        assert_eq!(parse_date("now", now(), timezone), Ok(now()));
+        assert_eq!(
+            parse_date("-2d", now(), timezone),
+            Ok(Utc.ymd(2024, 1, 8).and_hms(12, 0, 0))
+        );
+        assert_eq!(
+            parse_date("+1h", now(), timezone),
+            Ok(Utc.ymd(2024, 1, 10).and_hms(13, 0, 0))
+        );
+    }
+
+    #[test]
+    fn parses_iso_dates() {
+        let utc = DisplayTimezone::Utc;
+        assert_eq!(
+            parse_date("2024-01-01T10:00:00+01:00", now(), utc),
+            Ok(Utc.ymd(2024, 1, 1).and_hms(9, 0, 0))
+        );
+        assert_eq!(
+            parse_date("2024-01-01 10:30:00", now(), utc),
+            Ok(Utc.ymd(2024, 1, 1).and_hms(10, 30, 0))
+        );
+        assert_eq!(
+            parse_date("2024-01-01", now(), utc),
+            Ok(Utc.ymd(2024, 1, 1).and_hms(0, 0, 0))
+        );
+        let plus_two = DisplayTimezone::from_name("+02:00").unwrap();
+        assert_eq!(
+            parse_date("2024-01-01", now(), plus_two),
+            Ok(Utc.ymd(2023, 12, 31).and_hms(22, 0, 0))
+        );
+        assert!(parse_date("yesterday", now(), utc).is_err());
+        assert!(parse_date("2024-13-01", now(), utc).is_err());
+    }
+
+    #[test]
+    fn reads_stored_timestamps() {
+        let datetime = Utc.ymd(2024, 1, 1).and_hms_milli(9, 0, 0, 250);
+        assert_eq!(timestamp_value(datetime), Value::Float(1704099600.25));
+        assert_eq!(timestamp_datetime(&timestamp_value(datetime)), Some(datetime));
+        assert_eq!(
+            timestamp_datetime(&Value::Integer(1704099600.into())),
+            Some(Utc.ymd(2024, 1, 1).and_hms(9, 0, 0))
+        );
+        assert_eq!(timestamp_datetime(&Value::Text("now".to_string())), None);
+    }
+}
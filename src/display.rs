@@ -0,0 +1,441 @@
+use crate::date::{date_string, timestamp_datetime, DisplayTimezone};
+use crate::session::Session;
+use crate::validate::SYSTEM_FIELDS;
+use ciborium::value::Value;
+use rs_drive::contract::types::DocumentFieldType;
+use rs_drive::contract::{Contract, DocumentType};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+pub const IDENTIFIER_MEDIA_TYPE: &str = "application/x.dash.dpp.identifier";
+
+/// Shown in result tables for properties a document does not have
+pub const NULL_MARKER: &str = "∅";
+
+#[derive(Clone, Copy)]
+pub enum BytesEncoding {
+    Base58,
+    Hex,
+    Base64,
+}
+
+impl BytesEncoding {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "base58" => Some(BytesEncoding::Base58),
+            "hex" => Some(BytesEncoding::Hex),
+            "base64" => Some(BytesEncoding::Base64),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BytesEncoding::Base58 => "base58",
+            BytesEncoding::Hex => "hex",
+            BytesEncoding::Base64 => "base64",
+        }
+    }
+
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            BytesEncoding::Base58 => bs58::encode(bytes).into_string(),
+            BytesEncoding::Hex => hex::encode(bytes),
+            BytesEncoding::Base64 => base64::encode(bytes),
+        }
+    }
+}
+
+/// How values are rendered in result tables and item views.
+pub struct DisplaySettings {
+    /// Levels of nested arrays and maps rendered before they are elided
+    pub depth: usize,
+    /// Characters of text shown in result tables, 0 shows all of it
+    pub text_width: usize,
+    /// Encoding used for every byte array, when not set it follows the content media type
+    pub bytes_encoding: Option<BytesEncoding>,
+    /// Columns shown in result tables by document type name, all properties when not set
+    pub columns: HashMap<String, Vec<String>>,
+    /// Timezone dates are shown in, and dates without an offset are read in
+    pub timezone: DisplayTimezone,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            depth: 2,
+            text_width: 20,
+            bytes_encoding: None,
+            columns: HashMap::new(),
+            timezone: DisplayTimezone::Utc,
+        }
+    }
+}
+
+fn collect_media_types(
+    prefix: &str,
+    schema: &JsonValue,
+    media_types: &mut BTreeMap<String, String>,
+) {
+    let properties = match schema.get("properties").and_then(|p| p.as_object()) {
+        Some(properties) => properties,
+        None => return,
+    };
+    for (name, property) in properties.iter() {
+        let path = format!("{}.{}", prefix, name);
+        if let Some(media_type) = property.get("contentMediaType").and_then(|m| m.as_str()) {
+            media_types.insert(path.clone(), media_type.to_string());
+        }
+        collect_media_types(path.as_str(), property, media_types);
+    }
+}
+
+/// Reads the content media types of a contract file by `documentType.property`, nested
+/// properties continue the dotted path.
+pub fn content_media_types(contract_path: &str) -> BTreeMap<String, String> {
+    let mut media_types = BTreeMap::new();
+    let contract = fs::read_to_string(contract_path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<JsonValue>(data.as_str()).ok());
+    if let Some(documents) = contract
+        .as_ref()
+        .and_then(|contract| contract.get("documents"))
+        .and_then(|documents| documents.as_object())
+    {
+        for (name, document_type) in documents.iter() {
+            collect_media_types(name, document_type, &mut media_types);
+        }
+    }
+    media_types
+}
+
+/// Settings and contract information needed to render values.
+#[derive(Clone, Copy)]
+pub struct ValueFormat<'a> {
+    pub settings: &'a DisplaySettings,
+    /// Content media types of the contract the values come from
+    pub media_types: Option<&'a BTreeMap<String, String>>,
+    /// Show text and nested values without truncating them
+    pub full: bool,
+}
+
+impl<'a> ValueFormat<'a> {
+    fn depth(&self) -> usize {
+        if self.full {
+            usize::MAX
+        } else {
+            self.settings.depth
+        }
+    }
+
+    fn bytes_encoding(&self, path: &str) -> BytesEncoding {
+        if let Some(bytes_encoding) = self.settings.bytes_encoding {
+            return bytes_encoding;
+        }
+        match self
+            .media_types
+            .and_then(|media_types| media_types.get(path))
+        {
+            Some(media_type) if media_type == IDENTIFIER_MEDIA_TYPE => BytesEncoding::Base58,
+            _ => BytesEncoding::Hex,
+        }
+    }
+}
+
+fn integer_json(integer: i128) -> JsonValue {
+    if let Ok(integer) = i64::try_from(integer) {
+        JsonValue::from(integer)
+    } else if let Ok(integer) = u64::try_from(integer) {
+        JsonValue::from(integer)
+    } else {
+        JsonValue::String(integer.to_string())
+    }
+}
+
+/// Converts a value to json, byte arrays become strings in the encoding of their path and
+/// arrays and maps deeper than `depth` are replaced by their length.
+fn value_to_json(value: &Value, path: &str, depth: usize, format: ValueFormat) -> JsonValue {
+    match value {
+        Value::Integer(integer) => integer_json(i128::from(*integer)),
+        Value::Float(float) => Number::from_f64(*float)
+            .map(JsonValue::Number)
+            .unwrap_or_else(|| JsonValue::String(float.to_string())),
+        Value::Bytes(bytes) => JsonValue::String(format.bytes_encoding(path).encode(bytes)),
+        Value::Text(text) => JsonValue::String(text.clone()),
+        Value::Bool(b) => JsonValue::Bool(*b),
+        Value::Null => JsonValue::Null,
+        Value::Tag(tag, value) => {
+            let mut tagged = Map::new();
+            tagged.insert("tag".to_string(), JsonValue::from(*tag));
+            tagged.insert(
+                "value".to_string(),
+                value_to_json(value, path, depth, format),
+            );
+            JsonValue::Object(tagged)
+        }
+        Value::Array(array) if depth == 0 => JsonValue::String(format!("[...{}]", array.len())),
+        Value::Map(map) if depth == 0 => JsonValue::String(format!("{{...{}}}", map.len())),
+        Value::Array(array) => JsonValue::Array(
+            array
+                .iter()
+                .map(|value| value_to_json(value, path, depth - 1, format))
+                .collect(),
+        ),
+        Value::Map(map) => {
+            let mut object = Map::new();
+            for (key, value) in map.iter() {
+                let key = match key {
+                    Value::Text(text) => text.clone(),
+                    _ => value_string(key, path, None, format),
+                };
+                let value_path = format!("{}.{}", path, key);
+                object.insert(
+                    key,
+                    value_to_json(value, value_path.as_str(), depth - 1, format),
+                );
+            }
+            JsonValue::Object(object)
+        }
+        _ => JsonValue::Null,
+    }
+}
+
+/// Cuts text to the configured width on a character boundary, telling how long it was.
+fn truncated_text(text: &str, format: ValueFormat) -> String {
+    let width = format.settings.text_width;
+    if format.full || width == 0 {
+        return text.to_string();
+    }
+    let len = text.chars().count();
+    if len > width {
+        let first_text: String = text.chars().take(width).collect();
+        format!("{}[...({})]", first_text, len)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Removes a flag like `--full` from a command, telling whether it was there.
+pub fn take_flag(input: String, flag: &str) -> (String, bool) {
+    if !input.split_whitespace().any(|word| word == flag) {
+        return (input, false);
+    }
+    let input = input
+        .split(' ')
+        .filter(|word| *word != flag)
+        .collect::<Vec<&str>>()
+        .join(" ");
+    (input, true)
+}
+
+/// Renders a value for a result cell, `path` is `documentType.property` and picks the byte
+/// encoding, nested arrays and maps are shown as compact json.
+pub fn value_string(
+    value: &Value,
+    path: &str,
+    field_type: Option<&DocumentFieldType>,
+    format: ValueFormat,
+) -> String {
+    match value {
+        Value::Integer(_) | Value::Float(_)
+            if matches!(field_type, Some(DocumentFieldType::Date)) =>
+        {
+            match timestamp_datetime(value) {
+                Some(datetime) => date_string(datetime, format.settings.timezone),
+                None => value_string(value, path, None, format),
+            }
+        }
+        Value::Integer(integer) => {
+            let i: i128 = (*integer).into();
+            format!("{}", i)
+        }
+        Value::Bytes(bytes) => format.bytes_encoding(path).encode(bytes),
+        Value::Float(float) => format!("{}", float),
+        Value::Text(text) => truncated_text(text, format),
+        Value::Bool(b) => {
+            format!("{}", b)
+        }
+        Value::Null => "None".to_string(),
+        Value::Tag(tag, value) => {
+            format!(
+                "tag({}) {}",
+                tag,
+                value_string(value, path, field_type, format)
+            )
+        }
+        Value::Array(_) | Value::Map(_) => {
+            value_to_json(value, path, format.depth(), format).to_string()
+        }
+        _ => "".to_string(),
+    }
+}
+
+fn print_display_settings(session: &Session) {
+    println!("depth: {}", session.display.depth);
+    println!("width: {}", session.display.text_width);
+    println!(
+        "bytes: {}",
+        session
+            .display
+            .bytes_encoding
+            .map_or("auto", |bytes_encoding| bytes_encoding.name())
+    );
+    println!("timezone: {}", session.display.timezone.name());
+}
+
+pub fn prompt_display(input: String, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    match (args.get(1), args.get(2)) {
+        (None, _) => print_display_settings(session),
+        (Some(&"depth"), Some(depth)) => match depth.parse::<usize>() {
+            Ok(depth) => {
+                session.display.depth = depth;
+                print_display_settings(session);
+            }
+            Err(_) => println!("### ERROR! An integer was not provided for the depth"),
+        },
+        (Some(&"width"), Some(width)) => match width.parse::<usize>() {
+            Ok(width) => {
+                session.display.text_width = width;
+                print_display_settings(session);
+            }
+            Err(_) => println!("### ERROR! An integer was not provided for the width"),
+        },
+        (Some(&"bytes"), Some(&"auto")) => {
+            session.display.bytes_encoding = None;
+            print_display_settings(session);
+        }
+        (Some(&"bytes"), Some(name)) => match BytesEncoding::from_name(name) {
+            Some(bytes_encoding) => {
+                session.display.bytes_encoding = Some(bytes_encoding);
+                print_display_settings(session);
+            }
+            None => println!("### ERROR! Bytes can be shown as auto, base58, hex or base64"),
+        },
+        (Some(&"timezone"), Some(name)) => match DisplayTimezone::from_name(name) {
+            Some(timezone) => {
+                session.display.timezone = timezone;
+                print_display_settings(session);
+            }
+            None => println!("### ERROR! The timezone should be utc, local or an offset like +02:00"),
+        },
+        _ => println!(
+            "### ERROR! Use display depth <n>, display width <n>, display bytes <encoding> or display timezone <timezone>"
+        ),
+    }
+}
+
+/// Checks that columns name system fields or properties of the document type.
+pub fn check_columns(document_type: &DocumentType, columns: &[String]) -> Result<(), String> {
+    for column in columns.iter() {
+        let property_name = column.split('.').next().unwrap_or_default();
+        if !SYSTEM_FIELDS.contains(&column.as_str())
+            && !document_type.properties.contains_key(property_name)
+        {
+            return Err(format!("Property {} does not exist", column));
+        }
+    }
+    Ok(())
+}
+
+fn print_columns(session: &Session, document_type_name: &str) {
+    match session.display.columns.get(document_type_name) {
+        Some(columns) => println!("{} columns: {}", document_type_name, columns.join(", ")),
+        None => println!("{} columns: all", document_type_name),
+    }
+}
+
+/// Chooses the columns result tables show for a document type, nested properties are written
+/// with dotted paths.
+pub fn prompt_columns(input: String, contract: &Contract, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 && args.len() != 3 {
+        println!("### ERROR! A document type and optionally the columns should be provided");
+        return;
+    }
+    let document_type_name = args.get(1).unwrap();
+    let document_type = match contract.document_type_for_name(document_type_name) {
+        Ok(document_type) => document_type,
+        Err(_) => {
+            println!("### ERROR! Document type does not exist");
+            return;
+        }
+    };
+    match args.get(2) {
+        None => {}
+        Some(&"all") => {
+            session.display.columns.remove(*document_type_name);
+        }
+        Some(columns) => {
+            let columns: Vec<String> = columns
+                .split(',')
+                .filter(|column| !column.is_empty())
+                .map(|column| column.to_string())
+                .collect();
+            if let Err(e) = check_columns(document_type, &columns) {
+                println!("### ERROR! {}", e);
+                return;
+            }
+            session
+                .display
+                .columns
+                .insert(document_type_name.to_string(), columns);
+        }
+    }
+    print_columns(session, document_type_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(settings: &DisplaySettings, full: bool) -> ValueFormat {
+        ValueFormat {
+            settings,
+            media_types: None,
+            full,
+        }
+    }
+
+    #[test]
+    fn truncates_multibyte_text_on_character_boundaries() {
+        let settings = DisplaySettings {
+            text_width: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            truncated_text("héllo wörld", format(&settings, false)),
+            "hél[...(11)]"
+        );
+        assert_eq!(
+            truncated_text("日本語のテキスト", format(&settings, false)),
+            "日本語[...(8)]"
+        );
+        assert_eq!(
+            truncated_text("🦀🦀🦀🦀", format(&settings, false)),
+            "🦀🦀🦀[...(4)]"
+        );
+    }
+
+    #[test]
+    fn keeps_short_text_and_full_text() {
+        let settings = DisplaySettings {
+            text_width: 3,
+            ..Default::default()
+        };
+        assert_eq!(truncated_text("äöü", format(&settings, false)), "äöü");
+        assert_eq!(
+            truncated_text("héllo wörld", format(&settings, true)),
+            "héllo wörld"
+        );
+        let unlimited = DisplaySettings {
+            text_width: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            truncated_text("héllo wörld", format(&unlimited, false)),
+            "héllo wörld"
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use crate::contract::{
+    contract_command, execute_paged, populate_with_documents, print_contract_options, print_fees,
+    QUERY_PAGE_SIZE,
+};
+use crate::grove;
+use crate::registry::ContractRegistry;
+use crate::session::Session;
+use ciborium::value::Value;
+use indexmap::IndexMap;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rs_drive::common;
+use rs_drive::contract::{document::Document, Contract};
+use rs_drive::drive::Drive;
+use rs_drive::error::Error;
+use rs_drive::query::{DriveQuery, InternalClauses, OrderClause, WhereClause, WhereOperator};
+use rustyline::Editor;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::time::SystemTime;
+
+pub const DEFAULT_PARENT_DOMAIN: &str = "dash";
+
+const MAX_LABEL_LENGTH: usize = 63;
+
+struct Domain {
+    id: [u8; 32],
+    owner_id: [u8; 32],
+    preorder_id: [u8; 32],
+    label: String,
+    normalized_label: String,
+    normalized_parent_domain_name: String,
+    preorder_salt: [u8; 32],
+}
+
+impl Domain {
+    fn full_normalized_name(&self) -> String {
+        if self.normalized_parent_domain_name.is_empty() {
+            self.normalized_label.clone()
+        } else {
+            format!(
+                "{}.{}",
+                self.normalized_label, self.normalized_parent_domain_name
+            )
+        }
+    }
+
+    /// Double sha-256 of the salt followed by the full normalized domain name, as DPNS expects
+    /// in the `saltedDomainHash` of the preorder.
+    fn salted_domain_hash(&self) -> Vec<u8> {
+        let mut salted_name = self.preorder_salt.to_vec();
+        salted_name.extend_from_slice(self.full_normalized_name().as_bytes());
+        Sha256::digest(Sha256::digest(salted_name)).to_vec()
+    }
+
+    fn to_document(&self) -> Document {
+        let mut properties = BTreeMap::new();
+        properties.insert("label".to_string(), Value::Text(self.label.clone()));
+        properties.insert(
+            "normalizedLabel".to_string(),
+            Value::Text(self.normalized_label.clone()),
+        );
+        properties.insert(
+            "normalizedParentDomainName".to_string(),
+            Value::Text(self.normalized_parent_domain_name.clone()),
+        );
+        properties.insert(
+            "preorderSalt".to_string(),
+            Value::Bytes(self.preorder_salt.to_vec()),
+        );
+        // the unique identity record must be equal to the document owner
+        properties.insert(
+            "records".to_string(),
+            Value::Map(vec![(
+                Value::Text("dashUniqueIdentityId".to_string()),
+                Value::Bytes(self.owner_id.to_vec()),
+            )]),
+        );
+        properties.insert(
+            "subdomainRules".to_string(),
+            Value::Map(vec![(
+                Value::Text("allowSubdomains".to_string()),
+                Value::Bool(false),
+            )]),
+        );
+        Document {
+            id: self.id,
+            properties,
+            owner_id: self.owner_id,
+        }
+    }
+
+    fn to_preorder_document(&self) -> Document {
+        let mut properties = BTreeMap::new();
+        properties.insert(
+            "saltedDomainHash".to_string(),
+            Value::Bytes(self.salted_domain_hash()),
+        );
+        Document {
+            id: self.preorder_id,
+            properties,
+            owner_id: self.owner_id,
+        }
+    }
+}
+
+/// Builds a label matching `^[a-zA-Z0-9][a-zA-Z0-9-]{0,61}[a-zA-Z0-9]$` out of real names so
+/// that generated domains look like the ones users register.
+fn random_label(first_names: &[String], last_names: &[String], rng: &mut StdRng) -> String {
+    let first_name = first_names.choose(rng).unwrap();
+    let label = match rng.gen_range(0..4) {
+        0 => first_name.clone(),
+        1 => format!("{}-{}", first_name, last_names.choose(rng).unwrap()),
+        2 => format!("{}{}", first_name, last_names.choose(rng).unwrap()),
+        _ => format!("{}{}", first_name, rng.gen_range(0..10000)),
+    };
+    let label: String = label
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .map(|c| match rng.gen_range(0..3) {
+            0 => c.to_ascii_lowercase(),
+            _ => c,
+        })
+        .take(MAX_LABEL_LENGTH)
+        .collect();
+    let label = label.trim_matches('-').to_string();
+    if label.len() < 3 {
+        format!("{}{}", label, rng.gen_range(100..1000))
+    } else {
+        label
+    }
+}
+
+fn random_domains(
+    count: u32,
+    parent: &str,
+    registered_labels: &mut HashSet<String>,
+    rng: &mut StdRng,
+) -> Vec<Domain> {
+    let first_names =
+        common::text_file_strings("src/supporting_files/contract/family/first-names.txt");
+    let last_names =
+        common::text_file_strings("src/supporting_files/contract/family/last-names.txt");
+
+    let mut domains = vec![];
+    for _i in 0..count {
+        let mut label = random_label(&first_names, &last_names, rng);
+        // parentNameAndLabel is unique, keep suffixing until the normalized label is free
+        while registered_labels.contains(&label.to_lowercase()) {
+            if label.len() > MAX_LABEL_LENGTH - 4 {
+                label.truncate(MAX_LABEL_LENGTH - 4);
+            }
+            label = format!("{}{}", label, rng.gen_range(0..10000));
+        }
+        let normalized_label = label.to_lowercase();
+        registered_labels.insert(normalized_label.clone());
+        domains.push(Domain {
+            id: rng.gen::<[u8; 32]>(),
+            owner_id: rng.gen::<[u8; 32]>(),
+            preorder_id: rng.gen::<[u8; 32]>(),
+            label,
+            normalized_label,
+            normalized_parent_domain_name: parent.to_string(),
+            preorder_salt: rng.gen::<[u8; 32]>(),
+        });
+    }
+    domains
+}
+
+/// Gets the normalized labels already registered under a parent domain, paging through the
+/// parentNameAndLabel index.
+fn registered_labels(
+    drive: &Drive,
+    contract: &Contract,
+    parent: &str,
+) -> Result<HashSet<String>, Error> {
+    let document_type = contract.document_type_for_name("domain")?;
+    let mut equal_clauses = BTreeMap::new();
+    equal_clauses.insert(
+        "normalizedParentDomainName".to_string(),
+        WhereClause {
+            field: "normalizedParentDomainName".to_string(),
+            operator: WhereOperator::Equal,
+            value: Value::Text(parent.to_string()),
+        },
+    );
+    let mut order_by = IndexMap::new();
+    order_by.insert(
+        "normalizedLabel".to_string(),
+        OrderClause {
+            field: "normalizedLabel".to_string(),
+            ascending: true,
+        },
+    );
+    let query = DriveQuery {
+        contract,
+        document_type,
+        internal_clauses: InternalClauses {
+            primary_key_in_clause: None,
+            primary_key_equal_clause: None,
+            in_clause: None,
+            range_clause: None,
+            equal_clauses,
+        },
+        offset: 0,
+        limit: QUERY_PAGE_SIZE,
+        order_by,
+        start_at: None,
+        start_at_included: false,
+        block_time: None,
+    };
+    let (documents, _) = execute_paged(drive, query)?;
+    Ok(documents
+        .into_iter()
+        .filter_map(|document| match document.properties.get("normalizedLabel") {
+            Some(Value::Text(label)) => Some(label.clone()),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Registers `count` new domains under the parent, inserting for each a preorder and a domain
+/// document that reference each other through the salted domain hash.
+pub fn populate_domains(
+    count: u32,
+    parent: &str,
+    drive: &Drive,
+    contract: &Contract,
+    session: &mut Session,
+) -> Result<(i64, u64), Error> {
+    let mut labels = registered_labels(drive, contract, parent)?;
+    let domains = random_domains(count, parent, &mut labels, &mut session.rng());
+
+    let preorder_document_type = contract.document_type_for_name("preorder")?;
+    let preorders: Vec<Document> = domains
+        .iter()
+        .map(|domain| domain.to_preorder_document())
+        .collect();
+    let (preorder_storage_fee, preorder_processing_fee) = populate_with_documents(
+        preorders,
+        drive,
+        preorder_document_type,
+        contract,
+        true,
+        session,
+    )?;
+
+    let domain_document_type = contract.document_type_for_name("domain")?;
+    let documents: Vec<Document> = domains.iter().map(|domain| domain.to_document()).collect();
+    let (domain_storage_fee, domain_processing_fee) =
+        populate_with_documents(documents, drive, domain_document_type, contract, true, session)?;
+
+    Ok((
+        preorder_storage_fee + domain_storage_fee,
+        preorder_processing_fee + domain_processing_fee,
+    ))
+}
+
+fn print_dpns_options(contract: &Contract) {
+    print_contract_options(contract);
+    println!("### popdomains / pd <number> <option:parent_domain>              - register valid unique domains with their preorders");
+    println!();
+}
+
+fn prompt_populate_domains(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    session: &mut Session,
+) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 && args.len() != 3 {
+        println!("### ERROR! One or two parameters should be provided");
+    } else {
+        let count_str = args.get(1).unwrap();
+        let parent = args.get(2).unwrap_or(&DEFAULT_PARENT_DOMAIN).to_lowercase();
+        match count_str.parse::<u32>() {
+            Ok(value) => {
+                if value > 0 && value <= 10000 {
+                    let start_time = SystemTime::now();
+                    match populate_domains(value, parent.as_str(), drive, contract, session) {
+                        Ok((storage_fee, processing_fee)) => {
+                            if let Ok(n) = SystemTime::now().duration_since(start_time) {
+                                print_fees(storage_fee, processing_fee, value);
+                                println!("Time taken: {}", n.as_secs_f64());
+                            }
+                        }
+                        Err(e) => {
+                            println!("### ERROR! Could not register domains");
+                            println!("### Info {:?}", e);
+                        }
+                    }
+                } else {
+                    println!("### ERROR! Value must be between 1 and 10000");
+                }
+            }
+            Err(_) => {
+                println!("### ERROR! An integer was not provided for the population");
+            }
+        }
+    }
+}
+
+fn dpns_rl(
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
+    let readline = rl.readline("> ");
+    match readline {
+        Ok(input) => {
+            if input.starts_with("popdomains ") || input.starts_with("pd ") {
+                prompt_populate_domains(input, drive, contract, session);
+                grove::print_root_hash_if_enabled(drive, session);
+                true
+            } else {
+                contract_command(input, drive, contract, contracts, session, rl)
+            }
+        }
+        Err(_) => {
+            println!("no input, try again");
+            true
+        }
+    }
+}
+
+pub fn dpns_loop(
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
+    print_dpns_options(contract);
+    dpns_rl(drive, contract, contracts, session, rl)
+}
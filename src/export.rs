@@ -0,0 +1,250 @@
+use crate::grove::stored_contract_cbor;
+use rs_drive::contract::types::{ArrayFieldType, DocumentField, DocumentFieldType};
+use rs_drive::contract::{Contract, DocumentType};
+use rs_drive::drive::Drive;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::BTreeMap;
+use std::fs;
+
+fn length_schema(
+    mut schema: JsonValue,
+    min_key: &str,
+    max_key: &str,
+    min: &Option<usize>,
+    max: &Option<usize>,
+) -> JsonValue {
+    if let Some(min) = min {
+        schema[min_key] = json!(min);
+    }
+    if let Some(max) = max {
+        schema[max_key] = json!(max);
+    }
+    schema
+}
+
+/// Schema of the items of an array, written as `items` so the array keeps its item type.
+fn array_item_schema(item_type: &ArrayFieldType) -> JsonValue {
+    match item_type {
+        ArrayFieldType::Integer => json!({ "type": "integer" }),
+        ArrayFieldType::Number => json!({ "type": "number" }),
+        ArrayFieldType::Boolean => json!({ "type": "boolean" }),
+        ArrayFieldType::Date => json!({ "type": "integer" }),
+        ArrayFieldType::String(min_length, max_length) => length_schema(
+            json!({ "type": "string" }),
+            "minLength",
+            "maxLength",
+            min_length,
+            max_length,
+        ),
+        ArrayFieldType::ByteArray(min_items, max_items) => length_schema(
+            json!({ "type": "array", "byteArray": true }),
+            "minItems",
+            "maxItems",
+            min_items,
+            max_items,
+        ),
+    }
+}
+
+/// Keywords Drive does not parse, copied from the property schema of the contract file.
+const CARRIED_KEYWORDS: [&str; 2] = ["pattern", "contentMediaType"];
+
+/// Schema of a property, dates are written as integers the way contracts declare timestamps.
+/// `source` is the schema of the same property in the contract file, when it could be read.
+fn field_type_schema(field_type: &DocumentFieldType, source: Option<&JsonValue>) -> JsonValue {
+    let mut schema = match field_type {
+        DocumentFieldType::Integer => json!({ "type": "integer" }),
+        DocumentFieldType::Number => json!({ "type": "number" }),
+        DocumentFieldType::Boolean => json!({ "type": "boolean" }),
+        DocumentFieldType::Date => json!({ "type": "integer" }),
+        DocumentFieldType::String(min_length, max_length) => length_schema(
+            json!({ "type": "string" }),
+            "minLength",
+            "maxLength",
+            min_length,
+            max_length,
+        ),
+        DocumentFieldType::ByteArray(min_items, max_items) => length_schema(
+            json!({ "type": "array", "byteArray": true }),
+            "minItems",
+            "maxItems",
+            min_items,
+            max_items,
+        ),
+        DocumentFieldType::Object(fields) => {
+            let mut schema = properties_schema(fields, source);
+            schema["type"] = json!("object");
+            schema
+        }
+        DocumentFieldType::Array(item_type) => {
+            json!({ "type": "array", "items": array_item_schema(item_type) })
+        }
+    };
+    for keyword in CARRIED_KEYWORDS.iter() {
+        if let Some(value) = source.and_then(|source| source.get(keyword)) {
+            schema[*keyword] = value.clone();
+        }
+    }
+    schema
+}
+
+/// `source` is the schema holding the same properties in the contract file.
+fn properties_schema(
+    fields: &BTreeMap<String, DocumentField>,
+    source: Option<&JsonValue>,
+) -> JsonValue {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    for (name, field) in fields.iter() {
+        let source_property = source
+            .and_then(|source| source.get("properties"))
+            .and_then(|properties| properties.get(name));
+        properties.insert(
+            name.clone(),
+            field_type_schema(&field.document_type, source_property),
+        );
+        if field.required {
+            required.push(json!(name));
+        }
+    }
+    json!({
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// Name of the index with the same properties in the contract file, Drive does not keep it.
+fn source_index_name(
+    index_properties: &[JsonValue],
+    source: Option<&JsonValue>,
+) -> Option<JsonValue> {
+    source?
+        .get("indices")?
+        .as_array()?
+        .iter()
+        .find(|source_index| {
+            source_index
+                .get("properties")
+                .and_then(|properties| properties.as_array())
+                .map_or(false, |properties| {
+                    properties.as_slice() == index_properties
+                })
+        })?
+        .get("name")
+        .cloned()
+}
+
+fn document_type_schema(document_type: &DocumentType, source: Option<&JsonValue>) -> JsonValue {
+    let mut schema = properties_schema(&document_type.properties, source);
+    let indices: Vec<JsonValue> = document_type
+        .indices
+        .iter()
+        .map(|index| {
+            let properties: Vec<JsonValue> = index
+                .properties
+                .iter()
+                .map(|property| {
+                    let mut index_property = Map::new();
+                    let direction = if property.ascending { "asc" } else { "desc" };
+                    index_property.insert(property.name.clone(), json!(direction));
+                    JsonValue::Object(index_property)
+                })
+                .collect();
+            let mut index_schema = json!({ "properties": properties, "unique": index.unique });
+            if let Some(name) = source_index_name(&properties, source) {
+                index_schema["name"] = name;
+            }
+            index_schema
+        })
+        .collect();
+    schema["indices"] = json!(indices);
+    schema["documentsKeepHistory"] = json!(document_type.documents_keep_history);
+    schema["documentsMutable"] = json!(document_type.documents_mutable);
+    schema
+}
+
+fn read_contract_file(contract_path: &str) -> Option<JsonValue> {
+    let data = fs::read_to_string(contract_path).ok()?;
+    serde_json::from_str::<JsonValue>(data.as_str()).ok()
+}
+
+/// Rebuilds the json schema of the contract from what Drive parsed, keys are sorted so two
+/// dumps of the same contract are identical. Index names, patterns and content media types are
+/// not parsed by Drive, they are taken from `source`, the contract file, when it is given.
+pub fn contract_to_json(contract: &Contract, source: Option<&JsonValue>) -> JsonValue {
+    let mut documents = Map::new();
+    for (name, document_type) in contract.document_types.iter() {
+        let source_document_type = source
+            .and_then(|source| source.get("documents"))
+            .and_then(|documents| documents.get(name));
+        documents.insert(
+            name.clone(),
+            document_type_schema(document_type, source_document_type),
+        );
+    }
+    json!({
+        "$id": bs58::encode(contract.id).into_string(),
+        "documents": documents,
+    })
+}
+
+fn print_contract_id(contract: &Contract) {
+    println!(
+        "Contract id: {} (0x{})",
+        bs58::encode(contract.id).into_string(),
+        hex::encode(contract.id)
+    );
+}
+
+pub fn print_contract_json(drive: &Drive, contract: &Contract, contract_path: Option<&str>) {
+    let source = contract_path.and_then(read_contract_file);
+    let contract_json = contract_to_json(contract, source.as_ref());
+    let contract_json =
+        serde_json::to_string_pretty(&contract_json).expect("expected to serialize contract");
+    println!("{}", contract_json);
+    match (contract_path, &source) {
+        (Some(contract_path), Some(_)) => println!(
+            "Reconstructed from what Drive parsed, index names, patterns and content media types from {}",
+            contract_path
+        ),
+        _ => println!("Reconstructed from what Drive parsed, keywords it does not keep are missing"),
+    }
+    print_contract_id(contract);
+    println!("Json size: {} bytes", contract_json.len());
+    match stored_contract_cbor(drive, contract) {
+        Ok(contract_cbor) => println!("Stored cbor size: {} bytes", contract_cbor.len()),
+        Err(e) => {
+            println!("### ERROR! Could not get the stored contract");
+            println!("### Info {:?}", e);
+        }
+    }
+}
+
+pub fn prompt_write_cbor(path: Option<&str>, drive: &Drive, contract: &Contract) {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            println!("### ERROR! A file path should be provided");
+            return;
+        }
+    };
+    let contract_cbor = match stored_contract_cbor(drive, contract) {
+        Ok(contract_cbor) => contract_cbor,
+        Err(e) => {
+            println!("### ERROR! Could not get the stored contract");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    match fs::write(path, &contract_cbor) {
+        Ok(_) => {
+            print_contract_id(contract);
+            println!("Wrote {} bytes of cbor to {}", contract_cbor.len(), path);
+        }
+        Err(e) => {
+            println!("### ERROR! Could not write {}", path);
+            println!("### Info {:?}", e);
+        }
+    }
+}
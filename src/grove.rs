@@ -0,0 +1,698 @@
+use crate::contract::index_name;
+use crate::session::Session;
+use grovedb::query_result_type::QueryResultType;
+use grovedb::{Element, PathQuery, Query};
+use prettytable::{Cell, Row, Table};
+use rs_drive::contract::{document::Document, Contract, DocumentType, IndexProperty};
+use rs_drive::drive::document::contract_documents_primary_key_path;
+use rs_drive::drive::flags::StorageFlags;
+use rs_drive::drive::Drive;
+use rs_drive::error::drive::DriveError;
+use rs_drive::error::Error;
+use std::collections::BTreeMap;
+
+/// Gets the element Drive stored for a document under the primary key tree of its document type.
+pub fn document_element(
+    drive: &Drive,
+    contract: &Contract,
+    document_type_name: &str,
+    id: &[u8],
+) -> Result<Element, Error> {
+    let path = contract_documents_primary_key_path(&contract.id, document_type_name);
+    Ok(drive.grove.get(path, id, None)?)
+}
+
+/// Gets the storage flags of an element, documents keeping history are stored as a tree and
+/// report the flags of that tree.
+pub fn element_storage_flags(element: &Element) -> Option<StorageFlags> {
+    let flags = match element {
+        Element::Item(_, flags) => flags,
+        Element::Reference(_, flags) => flags,
+        Element::Tree(_, flags) => flags,
+    };
+    StorageFlags::from_element_flags(flags.clone())
+}
+
+/// Gets every key and element directly under a path.
+pub fn subtree_entries(drive: &Drive, path: &[Vec<u8>]) -> Result<Vec<(Vec<u8>, Element)>, Error> {
+    let mut query = Query::new();
+    query.insert_all();
+    let path_query = PathQuery::new_unsized(path.to_vec(), query);
+    let (results, _) = drive.grove.query_raw(
+        &path_query,
+        QueryResultType::QueryKeyElementPairResultType,
+        None,
+    )?;
+    Ok(results.to_key_elements())
+}
+
+pub fn element_at(drive: &Drive, path: &[Vec<u8>], key: &[u8]) -> Result<Element, Error> {
+    Ok(drive.grove.get(
+        path.iter().map(|segment| segment.as_slice()),
+        key,
+        None,
+    )?)
+}
+
+fn is_printable(key: &[u8]) -> bool {
+    !key.is_empty() && key.iter().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Shows 32 byte keys as base58 ids, printable keys as text and anything else as hex.
+pub fn key_string_representation(key: &[u8]) -> String {
+    if key.len() == 32 {
+        bs58::encode(key).into_string()
+    } else if is_printable(key) {
+        String::from_utf8_lossy(key).to_string()
+    } else {
+        format!("0x{}", hex::encode(key))
+    }
+}
+
+/// Parses a path segment written the way `key_string_representation` shows it.
+fn key_from_string(segment: &str) -> Option<Vec<u8>> {
+    if let Some(hex_string) = segment.strip_prefix("0x") {
+        return hex::decode(hex_string).ok();
+    }
+    match bs58::decode(segment).into_vec() {
+        Ok(bytes) if bytes.len() == 32 => Some(bytes),
+        _ => Some(segment.as_bytes().to_vec()),
+    }
+}
+
+fn path_string_representation(path: &[Vec<u8>]) -> String {
+    let segments: Vec<String> = path
+        .iter()
+        .map(|segment| key_string_representation(segment))
+        .collect();
+    format!("/{}", segments.join("/"))
+}
+
+/// Resolves a path relative to the current one, `/` starts from the root and `..` goes up.
+fn resolve_path(current: &[Vec<u8>], path_string: &str) -> Option<Vec<Vec<u8>>> {
+    let mut path = if path_string.starts_with('/') {
+        vec![]
+    } else {
+        current.to_vec()
+    };
+    for segment in path_string.split('/').filter(|segment| !segment.is_empty()) {
+        match segment {
+            "." => {}
+            ".." => {
+                path.pop();
+            }
+            _ => path.push(key_from_string(segment)?),
+        }
+    }
+    Some(path)
+}
+
+/// Explains what a key means in the way Drive lays out contracts and their documents.
+fn key_meaning(path: &[Vec<u8>], key: &[u8], element: &Element, contract: &Contract) -> String {
+    let is_document_type = |bytes: &[u8]| {
+        std::str::from_utf8(bytes).map_or(false, |name| contract.document_types.contains_key(name))
+    };
+    let is_index_property = |bytes: &[u8]| {
+        std::str::from_utf8(bytes).map_or(false, |name| {
+            contract.document_types.values().any(|document_type| {
+                document_type
+                    .indices
+                    .iter()
+                    .any(|index| index.properties.iter().any(|property| property.name == name))
+            })
+        })
+    };
+    let parent = path.last().map(|parent| parent.as_slice());
+    if path.is_empty() {
+        "root leaf".to_string()
+    } else if key == contract.id {
+        "contract".to_string()
+    } else if is_document_type(key) {
+        "document type".to_string()
+    } else if key == [0] && parent.map_or(false, is_document_type) {
+        "primary key, document ids".to_string()
+    } else if key == [0] {
+        "document ids".to_string()
+    } else if matches!(element, Element::Reference(..)) || (key.len() == 32 && parent == Some(&[0u8][..])) {
+        "document id".to_string()
+    } else if is_index_property(key) && !parent.map_or(false, is_index_property) {
+        "index property".to_string()
+    } else if parent.map_or(false, is_index_property) {
+        "index value".to_string()
+    } else {
+        "".to_string()
+    }
+}
+
+fn element_description(element: &Element) -> (&'static str, String) {
+    match element {
+        Element::Item(value, _) => ("item", format!("{} bytes", value.len())),
+        Element::Reference(reference_path, _) => {
+            ("reference", path_string_representation(reference_path))
+        }
+        Element::Tree(root_hash, _) => ("tree", hex::encode(root_hash)),
+    }
+}
+
+fn tree_ls(drive: &Drive, contract: &Contract, path: &[Vec<u8>]) {
+    match subtree_entries(drive, path) {
+        Ok(entries) => {
+            println!("{} ({} entries)", path_string_representation(path), entries.len());
+            let mut table = Table::new();
+            table.add_row(Row::new(vec![
+                Cell::new("key"),
+                Cell::new("type"),
+                Cell::new("meaning"),
+                Cell::new("value"),
+            ]));
+            for (key, element) in entries.iter() {
+                let (element_type, description) = element_description(element);
+                table.add_row(Row::new(vec![
+                    Cell::new(key_string_representation(key).as_str()),
+                    Cell::new(element_type),
+                    Cell::new(key_meaning(path, key, element, contract).as_str()),
+                    Cell::new(description.as_str()),
+                ]));
+            }
+            table.printstd();
+        }
+        Err(e) => {
+            println!("### ERROR! Could not list {}", path_string_representation(path));
+            println!("### Info {:?}", e);
+        }
+    }
+}
+
+fn tree_cat(drive: &Drive, path: &[Vec<u8>], key: &[u8]) {
+    match element_at(drive, path, key) {
+        Ok(element) => {
+            let (element_type, description) = element_description(&element);
+            println!("key: {}", key_string_representation(key));
+            println!("type: {}", element_type);
+            match element_storage_flags(&element) {
+                Some(storage_flags) => println!("storage epoch: {}", storage_flags.epoch),
+                None => println!("storage epoch: none"),
+            }
+            match &element {
+                Element::Item(value, _) => {
+                    println!("size: {}", description);
+                    match Document::from_cbor(value.as_slice(), None, None) {
+                        Ok(document) => {
+                            println!("document $id: {}", bs58::encode(document.id).into_string());
+                            println!(
+                                "document $ownerId: {}",
+                                bs58::encode(document.owner_id).into_string()
+                            );
+                            for (name, value) in document.properties.iter() {
+                                println!("#### {} : {:?}", name, value);
+                            }
+                        }
+                        Err(_) => println!("value: {}", hex::encode(value)),
+                    }
+                }
+                Element::Reference(..) => println!("points to: {}", description),
+                Element::Tree(..) => println!("root hash: {}", description),
+            }
+        }
+        Err(e) => {
+            println!("### ERROR! Could not get {}", key_string_representation(key));
+            println!("### Info {:?}", e);
+        }
+    }
+}
+
+pub fn prompt_tree(input: String, drive: &Drive, contract: &Contract, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    let (command, argument) = match (args.get(1), args.get(2)) {
+        (None, _) => ("ls", None),
+        (Some(&"ls"), path) | (Some(&"cd"), path) | (Some(&"cat"), path) | (Some(&"pwd"), path) => {
+            (args[1], path.copied())
+        }
+        (Some(path), None) => ("ls", Some(*path)),
+        _ => {
+            println!("### ERROR! Unknown tree command");
+            return;
+        }
+    };
+    let path = match argument {
+        None => session.tree_path.clone(),
+        Some(path_string) => match resolve_path(&session.tree_path, path_string) {
+            Some(path) => path,
+            None => {
+                println!("### ERROR! Could not decode path");
+                return;
+            }
+        },
+    };
+    match command {
+        "ls" => tree_ls(drive, contract, &path),
+        "cd" => {
+            if let Some((key, parent)) = path.split_last() {
+                match element_at(drive, parent, key) {
+                    Ok(Element::Tree(..)) => {}
+                    Ok(_) => {
+                        println!("### ERROR! {} is not a tree", key_string_representation(key));
+                        return;
+                    }
+                    Err(e) => {
+                        println!("### ERROR! Could not get {}", key_string_representation(key));
+                        println!("### Info {:?}", e);
+                        return;
+                    }
+                }
+            }
+            session.tree_path = path;
+            println!("{}", path_string_representation(&session.tree_path));
+        }
+        "cat" => match path.split_last() {
+            Some((key, parent)) => tree_cat(drive, parent, key),
+            None => println!("### ERROR! A key should be provided"),
+        },
+        _ => println!("{}", path_string_representation(&session.tree_path)),
+    }
+}
+
+pub fn root_hash(drive: &Drive) -> Result<Option<[u8; 32]>, Error> {
+    Ok(drive.grove.root_hash(None)?)
+}
+
+/// Gets the hash of the subtree holding every index and document of a document type.
+pub fn document_type_hash(
+    drive: &Drive,
+    contract: &Contract,
+    document_type_name: &str,
+) -> Result<[u8; 32], Error> {
+    let primary_key_path = contract_documents_primary_key_path(&contract.id, document_type_name);
+    let (document_type_path, _) = primary_key_path.split_at(3);
+    match drive.grove.get(document_type_path.iter().copied(), document_type_name.as_bytes(), None)? {
+        Element::Tree(hash, _) => Ok(hash),
+        _ => Err(Error::Drive(DriveError::CorruptedContractPath(
+            "document type should be stored as a tree",
+        ))),
+    }
+}
+
+/// Gets the contract as Drive stored it, under key 0 next to the documents tree.
+pub fn stored_contract_cbor(drive: &Drive, contract: &Contract) -> Result<Vec<u8>, Error> {
+    let document_type_name = contract.document_types.keys().next().ok_or(Error::Drive(
+        DriveError::CorruptedContractPath("contract should have document types"),
+    ))?;
+    let primary_key_path = contract_documents_primary_key_path(&contract.id, document_type_name);
+    let (contract_path, _) = primary_key_path.split_at(2);
+    match drive.grove.get(contract_path.iter().copied(), &[0], None)? {
+        Element::Item(contract_cbor, _) => Ok(contract_cbor),
+        _ => Err(Error::Drive(DriveError::CorruptedContractPath(
+            "contract keeping history is stored as a tree, not as an item",
+        ))),
+    }
+}
+
+fn print_root_hash(drive: &Drive) {
+    match root_hash(drive) {
+        Ok(Some(hash)) => println!("Root hash: {}", hex::encode(hash)),
+        Ok(None) => println!("Root hash: empty"),
+        Err(e) => {
+            println!("### ERROR! Could not get the root hash");
+            println!("### Info {:?}", e);
+        }
+    }
+}
+
+pub fn print_root_hash_if_enabled(drive: &Drive, session: &Session) {
+    if session.show_root_hash {
+        print_root_hash(drive);
+    }
+}
+
+pub fn prompt_root_hash(input: String, drive: &Drive, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    match args.get(1) {
+        None => print_root_hash(drive),
+        Some(&"on") => {
+            session.show_root_hash = true;
+            println!("The root hash will be shown after every change");
+        }
+        Some(&"off") => {
+            session.show_root_hash = false;
+            println!("The root hash will only be shown on request");
+        }
+        Some(_) => println!("### ERROR! Expected on or off"),
+    }
+}
+
+pub fn prompt_fingerprint(input: String, drive: &Drive, contract: &Contract) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 {
+        println!("### ERROR! One parameter should be provided");
+        return;
+    }
+    let document_type_name = args.get(1).unwrap();
+    if contract.document_type_for_name(document_type_name).is_err() {
+        println!("### ERROR! Document type does not exist");
+        return;
+    }
+    match document_type_hash(drive, contract, document_type_name) {
+        Ok(hash) => println!("{} fingerprint: {}", document_type_name, hex::encode(hash)),
+        Err(e) => {
+            println!("### ERROR! Could not get the document type subtree");
+            println!("### Info {:?}", e);
+        }
+    }
+}
+
+/// Rough per node overhead of a merk node on disk besides its key and value: the kv hash, and
+/// for each child link its hash, key and heights.
+const MERK_NODE_OVERHEAD: u64 = 32 + 2 * (32 + 2);
+
+#[derive(Default, Clone, Copy)]
+struct SubtreeStats {
+    nodes: u64,
+    key_bytes: u64,
+    value_bytes: u64,
+    /// Bytes of item values without their storage flags, the serialized documents.
+    item_bytes: u64,
+}
+
+impl SubtreeStats {
+    fn add(&mut self, key: &[u8], element: &Element) {
+        self.nodes += 1;
+        self.key_bytes += key.len() as u64;
+        self.value_bytes += element_value_size(element) as u64;
+        if let Element::Item(value, _) = element {
+            self.item_bytes += value.len() as u64;
+        }
+    }
+
+    fn merge(&mut self, other: &SubtreeStats) {
+        self.nodes += other.nodes;
+        self.key_bytes += other.key_bytes;
+        self.value_bytes += other.value_bytes;
+        self.item_bytes += other.item_bytes;
+    }
+
+    /// The key is stored twice, in the node and in the link of its parent.
+    fn estimated_disk_size(&self) -> u64 {
+        self.nodes * MERK_NODE_OVERHEAD + 2 * self.key_bytes + self.value_bytes
+    }
+}
+
+pub fn element_value_size(element: &Element) -> usize {
+    let (size, flags) = match element {
+        Element::Item(value, flags) => (value.len(), flags),
+        Element::Reference(path, flags) => (
+            path.iter().map(|segment| segment.len() + 1).sum::<usize>(),
+            flags,
+        ),
+        Element::Tree(hash, flags) => (hash.len(), flags),
+    };
+    size + flags.as_ref().map_or(0, |flags| flags.len())
+}
+
+fn document_type_path(contract: &Contract, document_type_name: &str) -> Vec<Vec<u8>> {
+    contract_documents_primary_key_path(&contract.id, document_type_name)
+        .iter()
+        .take(4)
+        .map(|segment| segment.to_vec())
+        .collect()
+}
+
+/// Counts every element of a subtree, including the ones of nested subtrees.
+fn subtree_stats(drive: &Drive, path: &[Vec<u8>], stats: &mut SubtreeStats) -> Result<(), Error> {
+    for (key, element) in subtree_entries(drive, path)? {
+        stats.add(&key, &element);
+        if let Element::Tree(..) = element {
+            let mut child_path = path.to_vec();
+            child_path.push(key);
+            subtree_stats(drive, &child_path, stats)?;
+        }
+    }
+    Ok(())
+}
+
+/// Follows an index from the document type tree: each property name holds a tree per value,
+/// which holds the next property or, at the end of the index, the `0` tree of document ids.
+/// Unique indices store a single reference at `0` instead of a tree.
+fn index_stats(
+    drive: &Drive,
+    path: &[Vec<u8>],
+    properties: &[IndexProperty],
+    stats: &mut SubtreeStats,
+) -> Result<(), Error> {
+    let (key, rest) = match properties.split_first() {
+        None => (vec![0], None),
+        Some((property, rest)) => (property.name.as_bytes().to_vec(), Some(rest)),
+    };
+    let element = match element_at(drive, path, &key) {
+        Ok(element) => element,
+        // nothing was inserted for this index yet
+        Err(_) => return Ok(()),
+    };
+    stats.add(&key, &element);
+    let mut child_path = path.to_vec();
+    child_path.push(key);
+    match rest {
+        None => {
+            if let Element::Tree(..) = element {
+                for (key, element) in subtree_entries(drive, &child_path)? {
+                    stats.add(&key, &element);
+                }
+            }
+        }
+        Some(rest) => {
+            for (value_key, element) in subtree_entries(drive, &child_path)? {
+                stats.add(&value_key, &element);
+                let mut value_path = child_path.clone();
+                value_path.push(value_key);
+                index_stats(drive, &value_path, rest, stats)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn stats_row(name: &str, stats: &SubtreeStats, raw_document_size: u64) -> Row {
+    let ratio = if raw_document_size > 0 {
+        format!(
+            "{:.2}",
+            stats.estimated_disk_size() as f64 / raw_document_size as f64
+        )
+    } else {
+        "-".to_string()
+    };
+    Row::new(vec![
+        Cell::new(name),
+        Cell::new(stats.nodes.to_string().as_str()),
+        Cell::new(stats.key_bytes.to_string().as_str()),
+        Cell::new(stats.value_bytes.to_string().as_str()),
+        Cell::new(stats.estimated_disk_size().to_string().as_str()),
+        Cell::new(ratio.as_str()),
+    ])
+}
+
+fn print_document_type_size(
+    drive: &Drive,
+    contract: &Contract,
+    document_type_name: &str,
+    document_type: &DocumentType,
+) -> Result<(), Error> {
+    let path = document_type_path(contract, document_type_name);
+
+    let mut primary_key_stats = SubtreeStats::default();
+    let mut primary_key_path = path.clone();
+    primary_key_path.push(vec![0]);
+    subtree_stats(drive, &primary_key_path, &mut primary_key_stats)?;
+    let raw_document_size = primary_key_stats.item_bytes;
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("subtree"),
+        Cell::new("nodes"),
+        Cell::new("key bytes"),
+        Cell::new("value bytes"),
+        Cell::new("est. disk bytes"),
+        Cell::new("vs raw cbor"),
+    ]));
+    table.add_row(stats_row("documents", &primary_key_stats, raw_document_size));
+
+    let mut index_total = SubtreeStats::default();
+    for index in document_type.indices.iter() {
+        let mut stats = SubtreeStats::default();
+        index_stats(drive, &path, &index.properties, &mut stats)?;
+        index_total.merge(&stats);
+        table.add_row(stats_row(
+            format!("i: {}", index_name(index)).as_str(),
+            &stats,
+            raw_document_size,
+        ));
+    }
+    table.add_row(stats_row("all indices", &index_total, raw_document_size));
+
+    // indices sharing their first properties share those trees, count them once here
+    let mut total = SubtreeStats::default();
+    subtree_stats(drive, &path, &mut total)?;
+    table.add_row(stats_row("total", &total, raw_document_size));
+
+    println!("## {} ({} bytes of document cbor)", document_type_name, raw_document_size);
+    table.printstd();
+    Ok(())
+}
+
+pub fn prompt_size(input: String, drive: &Drive, contract: &Contract) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    let document_types: Vec<(&String, &DocumentType)> = match args.get(1) {
+        None => contract.document_types.iter().collect(),
+        Some(document_type_name) => match contract.document_types.get_key_value(*document_type_name) {
+            Some(document_type) => vec![document_type],
+            None => {
+                println!("### ERROR! Document type does not exist");
+                return;
+            }
+        },
+    };
+    for (document_type_name, document_type) in document_types {
+        if let Err(e) = print_document_type_size(drive, contract, document_type_name, document_type) {
+            println!("### ERROR! Could not walk the {} subtrees", document_type_name);
+            println!("### Info {:?}", e);
+        }
+    }
+    println!("Indices sharing their first properties share trees, so the index rows can overlap");
+}
+
+#[derive(Default, Clone, Copy)]
+struct EpochStats {
+    documents: u64,
+    bytes: u64,
+}
+
+/// Adds up the elements under a primary key tree by the epoch of their storage flags, versions
+/// of documents keeping history add their bytes to their own epoch but are not counted again.
+fn add_epoch_stats(
+    drive: &Drive,
+    path: &[Vec<u8>],
+    count_documents: bool,
+    stats: &mut BTreeMap<Option<u16>, EpochStats>,
+) -> Result<(), Error> {
+    for (key, element) in subtree_entries(drive, path)? {
+        let epoch = element_storage_flags(&element).map(|storage_flags| storage_flags.epoch);
+        let epoch_stats = stats.entry(epoch).or_default();
+        if count_documents {
+            epoch_stats.documents += 1;
+        }
+        epoch_stats.bytes += (key.len() + element_value_size(&element)) as u64;
+        if let Element::Tree(..) = element {
+            let mut child_path = path.to_vec();
+            child_path.push(key);
+            add_epoch_stats(drive, &child_path, false, stats)?;
+        }
+    }
+    Ok(())
+}
+
+fn epoch_row(name: &str, epoch: Option<u16>, stats: &EpochStats) -> Row {
+    let epoch = epoch.map_or("none".to_string(), |epoch| epoch.to_string());
+    Row::new(vec![
+        Cell::new(name),
+        Cell::new(epoch.as_str()),
+        Cell::new(stats.documents.to_string().as_str()),
+        Cell::new(stats.bytes.to_string().as_str()),
+    ])
+}
+
+/// Shows how many documents and bytes were stored in each epoch, the epoch a document was
+/// written in decides who is paid its storage fee over time.
+pub fn print_epochs(drive: &Drive, contract: &Contract) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("document type"),
+        Cell::new("epoch"),
+        Cell::new("documents"),
+        Cell::new("bytes"),
+    ]));
+    let mut totals: BTreeMap<Option<u16>, EpochStats> = BTreeMap::new();
+    for document_type_name in contract.document_types.keys() {
+        let mut path = document_type_path(contract, document_type_name);
+        path.push(vec![0]);
+        let mut stats = BTreeMap::new();
+        if let Err(e) = add_epoch_stats(drive, &path, true, &mut stats) {
+            println!("### ERROR! Could not walk the {} documents", document_type_name);
+            println!("### Info {:?}", e);
+            return;
+        }
+        for (epoch, epoch_stats) in stats.iter() {
+            table.add_row(epoch_row(document_type_name, *epoch, epoch_stats));
+            let total = totals.entry(*epoch).or_default();
+            total.documents += epoch_stats.documents;
+            total.bytes += epoch_stats.bytes;
+        }
+    }
+    for (epoch, epoch_stats) in totals.iter() {
+        table.add_row(epoch_row("all", *epoch, epoch_stats));
+    }
+    table.printstd();
+    println!("Bytes are the keys and values of the documents, index entries are not included");
+}
+
+/// Adds the references the indices of a document type keep to a document by the epoch they
+/// were stored in. The value trees leading to them are shared with other documents and stay.
+fn add_index_reference_stats(
+    drive: &Drive,
+    contract: &Contract,
+    document_type: &DocumentType,
+    document: &Document,
+    stats: &mut BTreeMap<Option<u16>, EpochStats>,
+) -> Result<(), Error> {
+    for index in document_type.indices.iter() {
+        let mut path = document_type_path(contract, document_type.name.as_str());
+        for property in index.properties.iter() {
+            let value = document.get_raw_for_document_type(
+                property.name.as_str(),
+                document_type,
+                Some(&document.owner_id),
+            )?;
+            path.push(property.name.as_bytes().to_vec());
+            path.push(value.unwrap_or_default());
+        }
+        // unique entries are a reference at `0`, others a `0` tree of references by id
+        let (key, element) = match element_at(drive, &path, &[0]) {
+            Ok(Element::Tree(..)) => {
+                path.push(vec![0]);
+                match element_at(drive, &path, &document.id) {
+                    Ok(element) => (document.id.to_vec(), element),
+                    Err(_) => continue,
+                }
+            }
+            Ok(element) => (vec![0], element),
+            Err(_) => continue,
+        };
+        let epoch = element_storage_flags(&element).map(|storage_flags| storage_flags.epoch);
+        stats.entry(epoch).or_default().bytes += (key.len() + element_value_size(&element)) as u64;
+    }
+    Ok(())
+}
+
+/// Bytes a document takes by the epoch they were stored in: its item under the primary key
+/// tree, every version of documents keeping history, and the references its indices keep.
+pub fn document_epoch_bytes(
+    drive: &Drive,
+    contract: &Contract,
+    document_type: &DocumentType,
+    document: &Document,
+) -> Result<BTreeMap<Option<u16>, u64>, Error> {
+    let document_type_name = document_type.name.as_str();
+    let id = document.id.as_slice();
+    let element = document_element(drive, contract, document_type_name, id)?;
+    let mut stats: BTreeMap<Option<u16>, EpochStats> = BTreeMap::new();
+    let epoch = element_storage_flags(&element).map(|storage_flags| storage_flags.epoch);
+    stats.entry(epoch).or_default().bytes += (id.len() + element_value_size(&element)) as u64;
+    if let Element::Tree(..) = element {
+        let mut path = document_type_path(contract, document_type_name);
+        path.push(vec![0]);
+        path.push(id.to_vec());
+        add_epoch_stats(drive, &path, false, &mut stats)?;
+    }
+    add_index_reference_stats(drive, contract, document_type, document, &mut stats)?;
+    Ok(stats
+        .into_iter()
+        .map(|(epoch, epoch_stats)| (epoch, epoch_stats.bytes))
+        .collect())
+}
@@ -1,11 +1,34 @@
+mod aggregate;
 mod contract;
+mod dashpay;
+mod date;
+mod display;
+mod dpns;
+mod export;
+mod grove;
 pub mod person;
+mod refund;
+mod registry;
+mod session;
+mod sql;
+mod upgrade;
+mod validate;
+mod whatif;
+mod wizard;
 
-use crate::contract::contract_loop;
+use crate::contract::{contract_loop, print_worst_case_fees};
+use crate::dashpay::dashpay_loop;
+use crate::dpns::dpns_loop;
 use crate::person::person_loop;
-use crate::ContractType::{DPNSContract, DashPayContract, OtherContract, PersonContract};
-use rand::{Rng, SeedableRng};
-use rs_drive::common;
+use crate::registry::{
+    alias_from_path, apply_contract_file, print_contracts, prompt_use, ContractRegistry,
+    ContractType, LoadedContract,
+};
+use crate::session::Session;
+use crate::upgrade::prompt_diff_contract;
+use crate::validate::prompt_validate;
+use crate::wizard::contract_wizard;
+use crate::registry::ContractType::{DPNSContract, DashPayContract, OtherContract, PersonContract};
 use rs_drive::contract::{Contract, document::Document, DocumentType};
 use rs_drive::drive::Drive;
 use rs_drive::query::{DriveQuery, InternalClauses, OrderClause};
@@ -15,11 +38,18 @@ use std::collections::HashMap;
 use std::default::Default;
 use std::fs;
 use std::path::Path;
+use std::rc::Rc;
 use rs_drive::error::Error;
 use tempdir::TempDir;
 
 pub const LAST_CONTRACT_PATH: &str = "last_contract_path";
 
+const PERSON_CONTRACT_PATH: &str = "src/supporting_files/contract/family/family-contract.json";
+
+const DASHPAY_CONTRACT_PATH: &str = "src/supporting_files/contract/dashpay-contract.json";
+
+const DPNS_CONTRACT_PATH: &str = "src/supporting_files/contract/dpns-contract.json";
+
 struct Explorer {
     config: HashMap<String, String>,
 }
@@ -44,117 +74,154 @@ impl Explorer {
         fs::write(path, config).unwrap();
     }
 
-    fn load_last_contract(&self, drive: &Drive) -> Option<Contract> {
-        let last_contract_path = self.config.get(LAST_CONTRACT_PATH)?;
-        let db_transaction = drive.grove.start_transaction();
-
-        let mut rng = rand::rngs::StdRng::from_entropy();
-        let contract_id = rng.gen::<[u8; 32]>();
-        let contract = common::setup_contract(
-            &drive,
-            last_contract_path,
-            Some(contract_id),
-            Some(&db_transaction),
-        );
-        drive.grove.commit_transaction(db_transaction).ok();
-        Some(contract)
-    }
-
     fn load_contract(&mut self, drive: &Drive, contract_path: &str) -> Result<Contract, Error> {
-        let db_transaction = drive.grove.start_transaction();
-
-        let mut rng = rand::rngs::StdRng::from_entropy();
-        let contract_id = rng.gen::<[u8; 32]>();
-        let contract = common::setup_contract(
-            &drive,
-            contract_path,
-            Some(contract_id),
-            Some(&db_transaction),
-        );
-        drive.commit_transaction(db_transaction)?;
+        let contract = apply_contract_file(drive, contract_path)?;
         self.config
             .insert(LAST_CONTRACT_PATH.to_string(), contract_path.to_string());
         self.save_config();
         Ok(contract)
     }
 
-    fn load_person_contract(&mut self, drive: &Drive) -> Result<Contract, Error> {
-        self.load_contract(
-            drive,
-            "src/supporting_files/contract/family/family-contract.json",
-        )
-    }
-
-    fn load_dashpay_contract(&mut self, drive: &Drive) -> Result<Contract, Error> {
-        self.load_contract(drive, "src/supporting_files/contract/dashpay-contract.json")
-    }
-
-    fn load_dpns_contract(&mut self, drive: &Drive) -> Result<Contract, Error> {
-        self.load_contract(drive, "src/supporting_files/contract/dpns-contract.json")
+    /// Switches to the contract already loaded from this path, or loads it under a new alias
+    /// when there is none or another alias is asked for. Returns the alias of the contract in use.
+    fn open_contract(
+        &mut self,
+        drive: &Drive,
+        registry: &mut ContractRegistry,
+        session: &mut Session,
+        contract_type: ContractType,
+        contract_path: &str,
+        alias: Option<&str>,
+    ) -> Option<String> {
+        if let Some(loaded_alias) = registry.alias_for_path(contract_path) {
+            if alias.map_or(true, |alias| alias == loaded_alias) {
+                println!("Using contract {}", loaded_alias);
+                session.current_contract = Some(loaded_alias.clone());
+                return Some(loaded_alias);
+            }
+        }
+        match self.load_contract(drive, contract_path) {
+            Ok(contract) => {
+                let contract_type = match contract_type {
+                    OtherContract => ContractType::from_document_types(&contract),
+                    contract_type => contract_type,
+                };
+                let alias = registry.unused_alias(
+                    alias
+                        .map(|alias| alias.to_string())
+                        .unwrap_or_else(|| alias_from_path(contract_path))
+                        .as_str(),
+                );
+                println!("Loaded contract as {}", alias);
+                registry.insert(
+                    alias.clone(),
+                    LoadedContract::new(contract_type, contract_path, contract),
+                );
+                session.current_contract = Some(alias.clone());
+                Some(alias)
+            }
+            Err(_) => {
+                println!("### ERROR! Issue loading contract");
+                None
+            }
+        }
     }
 
     fn base_rl(
         &mut self,
         drive: &Drive,
+        registry: &mut ContractRegistry,
+        session: &mut Session,
         rl: &mut Editor<()>,
-    ) -> (bool, Option<(ContractType, Contract)>) {
+    ) -> bool {
         let readline = rl.readline("> ");
         match readline {
             Ok(input) => {
                 if input.eq("person") || input.eq("p") {
-                    (
-                        true,
-                        Some((
-                            PersonContract,
-                            self.load_person_contract(drive)
-                                .expect("expected to load person contract"),
-                        )),
-                    )
+                    self.open_contract(
+                        drive,
+                        registry,
+                        session,
+                        PersonContract,
+                        PERSON_CONTRACT_PATH,
+                        Some("person"),
+                    );
                 } else if input.eq("dashpay") || input.eq("dp") {
-                    (
-                        true,
-                        Some((
-                            DashPayContract,
-                            self.load_dashpay_contract(drive)
-                                .expect("expected to load person contract"),
-                        )),
-                    )
+                    self.open_contract(
+                        drive,
+                        registry,
+                        session,
+                        DashPayContract,
+                        DASHPAY_CONTRACT_PATH,
+                        Some("dashpay"),
+                    );
                 } else if input.eq("dpns") {
-                    (
-                        true,
-                        Some((
-                            DPNSContract,
-                            self.load_dpns_contract(drive)
-                                .expect("expected to load person contract"),
-                        )),
-                    )
+                    self.open_contract(
+                        drive,
+                        registry,
+                        session,
+                        DPNSContract,
+                        DPNS_CONTRACT_PATH,
+                        Some("dpns"),
+                    );
                 } else if input.starts_with("l ") || input.starts_with("load ") {
-                    match prompt_load_contract(input) {
-                        None => (true, None),
+                    if let Some((contract_path, alias)) = prompt_load_contract(input) {
+                        self.open_contract(
+                            drive,
+                            registry,
+                            session,
+                            OtherContract,
+                            contract_path.as_str(),
+                            alias.as_deref(),
+                        );
+                    }
+                } else if input == "ll" || input == "loadlast" {
+                    match self.config.get(LAST_CONTRACT_PATH).cloned() {
                         Some(contract_path) => {
-                            match self.load_contract(drive, contract_path.as_str()) {
-                                Ok(contract) => (true, Some((OtherContract, contract))),
-                                Err(_) => {
-                                    println!("### ERROR! Issue loading contract");
-                                    (true, None)
-                                }
-                            }
+                            self.open_contract(
+                                drive,
+                                registry,
+                                session,
+                                OtherContract,
+                                contract_path.as_str(),
+                                None,
+                            );
                         }
+                        None => println!("### ERROR! No contract was loaded before"),
                     }
-                } else if input == "ll" || input == "loadlast" {
-                    match self.load_last_contract(drive) {
-                        Some(contract) => (true, Some((OtherContract, contract))),
-                        None => (true, None),
+                } else if input == "newcontract" {
+                    if let Some(contract_path) = contract_wizard(rl) {
+                        if let Some(alias) = self.open_contract(
+                            drive,
+                            registry,
+                            session,
+                            OtherContract,
+                            contract_path.as_str(),
+                            None,
+                        ) {
+                            let contract = &registry.get(&alias).unwrap().contract;
+                            for document_type_name in contract.document_types.keys() {
+                                println!("For {} document type:", document_type_name);
+                                print_worst_case_fees(drive, contract, document_type_name);
+                            }
+                        }
                     }
+                } else if input.starts_with("validate ") {
+                    prompt_validate(input);
+                } else if input.starts_with("diffcontract ") {
+                    prompt_diff_contract(input);
+                } else if input.starts_with("use ") {
+                    prompt_use(input, registry, session);
+                } else if input == "contracts" {
+                    print_contracts(drive, registry, session);
                 } else if input == "exit" {
-                    (false, None)
-                } else {
-                    (true, None)
+                    return false;
                 }
+                true
             }
             Err(_) => {
                 println!("no input, try again");
-                (true, None)
+                true
             }
         }
     }
@@ -162,20 +229,15 @@ impl Explorer {
     fn base_loop(
         &mut self,
         drive: &Drive,
+        registry: &mut ContractRegistry,
+        session: &mut Session,
         rl: &mut Editor<()>,
-    ) -> (bool, Option<(ContractType, Contract)>) {
+    ) -> bool {
         print_base_options();
-        self.base_rl(drive, rl)
+        self.base_rl(drive, registry, session, rl)
     }
 }
 
-enum ContractType {
-    PersonContract,
-    DashPayContract,
-    DPNSContract,
-    OtherContract,
-}
-
 fn print_welcome() {
     println!();
     println!();
@@ -197,18 +259,28 @@ fn print_base_options() {
     println!("### person / p                      - load the person contract");
     println!("### dashpay                         - load the dashpay contract");
     println!("### dpns                            - load the dpns contract");
-    println!("### load / l <contract file path>   - load a specific contract");
+    println!("### load / l <contract file path> <option:alias>");
+    println!("###                                   - load a specific contract");
     println!("### loadlast / ll                   - load the last loaded contract");
+    println!("### validate <contract file path>   - check a contract before loading it");
+    println!("### newcontract                     - create a contract step by step and load it");
+    println!("### diffcontract <old path> <new path>");
+    println!("###                                   - compare two versions of a contract");
+    println!("### contracts                       - list the loaded contracts");
+    println!("### use <alias>                     - go back to a loaded contract");
     println!();
 }
 
-fn prompt_load_contract(input: String) -> Option<String> {
-    let args = input.split_whitespace();
-    if args.count() != 2 {
-        println!("### ERROR! Two parameter should be provided");
+fn prompt_load_contract(input: String) -> Option<(String, Option<String>)> {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 && args.len() != 3 {
+        println!("### ERROR! A contract path and optionally an alias should be provided");
         None
     } else {
-        input.split_whitespace().last().map(|a| a.to_string())
+        Some((
+            args.get(1).unwrap().to_string(),
+            args.get(2).map(|alias| alias.to_string()),
+        ))
     }
 }
 
@@ -223,34 +295,41 @@ fn main() {
     let mut rl = rustyline::Editor::<()>::new();
     rl.set_auto_add_history(true);
 
-    let mut current_contract: Option<(ContractType, Contract)> = None;
+    let mut contracts = ContractRegistry::default();
 
     let mut explorer = Explorer::load_config();
 
+    let mut session = Session::default();
+
     loop {
-        if current_contract.is_some() {
-            match &current_contract {
-                None => {}
-                Some((contract_type, contract)) => match contract_type {
-                    ContractType::PersonContract => {
-                        if !person_loop(&drive, contract, &mut rl) {
-                            current_contract = None;
-                        }
+        let current_alias = session.current_contract.clone();
+        match current_alias.and_then(|alias| contracts.get(alias.as_str())) {
+            Some(loaded) => {
+                // shares the contract so commands like upgrade can change the registry
+                let contract_type = loaded.contract_type;
+                let contract = Rc::clone(&loaded.contract);
+                let stay = match contract_type {
+                    PersonContract => {
+                        person_loop(&drive, &contract, &mut contracts, &mut session, &mut rl)
                     }
-                    _ => {
-                        if !contract_loop(&drive, contract, &mut rl) {
-                            current_contract = None;
-                        }
+                    DashPayContract => {
+                        dashpay_loop(&drive, &contract, &mut contracts, &mut session, &mut rl)
+                    }
+                    DPNSContract => {
+                        dpns_loop(&drive, &contract, &mut contracts, &mut session, &mut rl)
                     }
-                },
+                    OtherContract => {
+                        contract_loop(&drive, &contract, &mut contracts, &mut session, &mut rl)
+                    }
+                };
+                if !stay {
+                    session.current_contract = None;
+                }
             }
-        } else {
-            let base_result = explorer.base_loop(&drive, &mut rl);
-            match base_result.0 {
-                true => {
-                    current_contract = base_result.1;
+            None => {
+                if !explorer.base_loop(&drive, &mut contracts, &mut session, &mut rl) {
+                    break;
                 }
-                false => break,
             }
         }
     }
@@ -1,3 +1,7 @@
+use crate::contract::{contract_command, is_mutating_command};
+use crate::grove;
+use crate::registry::ContractRegistry;
+use crate::session::{prompt_epoch, prompt_seed, Session};
 use grovedb::{Transaction};
 use indexmap::IndexMap;
 use rand::seq::SliceRandom;
@@ -36,11 +40,16 @@ struct Person {
 }
 
 impl Person {
-    fn new_with_random_ids(first_name: &str, middle_name: &str, last_name: &str, age: u8) -> Self {
-        let mut rng = rand::rngs::StdRng::from_entropy();
+    fn new_with_random_ids(
+        first_name: &str,
+        middle_name: &str,
+        last_name: &str,
+        age: u8,
+        session: &mut Session,
+    ) -> Self {
         Person {
-            id: Vec::from(rng.gen::<[u8; 32]>()),
-            owner_id: Vec::from(rng.gen::<[u8; 32]>()),
+            id: Vec::from(session.rng().gen::<[u8; 32]>()),
+            owner_id: Vec::from(session.owner_id_or_random()),
             first_name: first_name.to_string(),
             middle_name: middle_name.to_string(),
             last_name: last_name.to_string(),
@@ -117,9 +126,9 @@ impl Person {
         }
     }
 
-    fn add_single(&self, drive: &Drive, contract: &Contract) -> (i64, u64) {
+    fn add_single(&self, drive: &Drive, contract: &Contract, epoch: u16) -> (i64, u64) {
         let db_transaction = drive.grove.start_transaction();
-        let result = self.add_on_transaction(drive, contract, &db_transaction);
+        let result = self.add_on_transaction(drive, contract, epoch, &db_transaction);
         drive
             .grove
             .commit_transaction(db_transaction)
@@ -135,9 +144,10 @@ impl Person {
         &self,
         drive: &Drive,
         contract: &Contract,
+        epoch: u16,
         db_transaction: &Transaction,
     ) -> (i64, u64) {
-        let storage_flags = StorageFlags { epoch: 0 };
+        let storage_flags = StorageFlags { epoch };
         let value = serde_json::to_value(&self).expect("serialized person");
         let document_cbor =
             common::value_to_cbor(value, Some(rs_drive::drive::defaults::PROTOCOL_VERSION));
@@ -175,12 +185,18 @@ impl Person {
     }
 }
 
-pub fn populate(count: u32, drive: &Drive, contract: &Contract) -> Result<(), Error> {
+pub fn populate(
+    count: u32,
+    drive: &Drive,
+    contract: &Contract,
+    epoch: u16,
+    seed: Option<u64>,
+) -> Result<(), Error> {
     let db_transaction = drive.grove.start_transaction();
 
-    let people = Person::random_people(count, None);
+    let people = Person::random_people(count, seed);
     for person in people {
-        person.add_on_transaction(drive, contract, &db_transaction);
+        person.add_on_transaction(drive, contract, epoch, &db_transaction);
     }
     drive.commit_transaction(db_transaction)?;
 
@@ -216,10 +232,19 @@ fn print_person_contract_options() {
     println!(
         "### cost <document_type_name>                         - get the worst case scenario insertion cost"
     );
+    println!("### epoch <option:number>                              - show or set the epoch new people are stored in");
+    println!("### seed <option:number|off>                           - generate the same ids and people when replaying commands");
+    println!("### roothash <option:on|off>                           - show the GroveDB root hash, or always after changes");
+    println!("### fingerprint <document_type>                        - show the hash of the document type subtree");
+    println!("### whoami <option:identity_id|none>                   - show or set the identity owning inserted people");
+    println!("### identity new                                       - create a new identity and use it");
+    println!("### mine person                                        - get the people owned by the session identity");
+    println!("### contracts                                          - list the loaded contracts");
+    println!("### use <alias>                                        - switch to another loaded contract");
     println!();
 }
 
-fn prompt_populate(input: String, drive: &Drive, contract: &Contract) {
+fn prompt_populate(input: String, drive: &Drive, contract: &Contract, session: &mut Session) {
     let args: Vec<&str> = input.split_whitespace().collect();
     if args.len() != 2 {
         println!("### ERROR! Only one parameter should be provided");
@@ -228,7 +253,9 @@ fn prompt_populate(input: String, drive: &Drive, contract: &Contract) {
             Ok(value) => {
                 if value > 0 && value <= 5000 {
                     let start_time = SystemTime::now();
-                    populate(value, drive, contract).expect("populate returned an error");
+                    let seed = session.next_seed();
+                    populate(value, drive, contract, session.epoch, seed)
+                        .expect("populate returned an error");
                     if let Ok(n) = SystemTime::now().duration_since(start_time) {
                         println!("Time taken: {}", n.as_secs_f64());
                     }
@@ -243,7 +270,7 @@ fn prompt_populate(input: String, drive: &Drive, contract: &Contract) {
     }
 }
 
-fn prompt_insert(input: String, drive: &Drive, contract: &Contract) {
+fn prompt_insert(input: String, drive: &Drive, contract: &Contract, session: &mut Session) {
     let args = input.split_whitespace();
     if args.count() != 5 {
         println!("### ERROR! Four parameter should be provided");
@@ -257,9 +284,15 @@ fn prompt_insert(input: String, drive: &Drive, contract: &Contract) {
             Ok(age) => {
                 if age <= 150 {
                     let start_time = SystemTime::now();
+                    let person = Person::new_with_random_ids(
+                        first_name,
+                        middle_name,
+                        last_name,
+                        age,
+                        session,
+                    );
                     let (storage_fee, processing_fee) =
-                        Person::new_with_random_ids(first_name, middle_name, last_name, age)
-                            .add_single(drive, contract);
+                        person.add_single(drive, contract, session.epoch);
                     if let Ok(n) = SystemTime::now().duration_since(start_time) {
                         println!(
                             "Storage fee: {} ({})",
@@ -450,18 +483,25 @@ fn prompt_all(input: String, drive: &Drive, contract: &Contract) {
     }
 }
 
-fn person_rl(drive: &Drive, contract: &Contract, rl: &mut Editor<()>) -> bool {
+fn person_rl(
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
     let readline = rl.readline("> ");
     match readline {
         Ok(input) => {
-            if input.starts_with("pop ") {
-                prompt_populate(input, &drive, &contract);
+            let mutating = is_mutating_command(input.as_str());
+            let result = if input.starts_with("pop ") {
+                prompt_populate(input, &drive, &contract, session);
                 true
             } else if input.starts_with("all") {
                 prompt_all(input, &drive, &contract);
                 true
             } else if input.starts_with("insert ") {
-                prompt_insert(input, &drive, &contract);
+                prompt_insert(input, &drive, &contract, session);
                 true
             } else if input.starts_with("delete ") {
                 prompt_delete(input, &drive, &contract);
@@ -472,11 +512,35 @@ fn person_rl(drive: &Drive, contract: &Contract, rl: &mut Editor<()>) -> bool {
             } else if input.starts_with("cost ") {
                 prompt_cost(input, &drive, &contract);
                 true
+            } else if input == "epoch" || input.starts_with("epoch ") {
+                prompt_epoch(input, session);
+                true
+            } else if input == "seed" || input.starts_with("seed ") {
+                prompt_seed(input, session);
+                true
+            } else if input == "roothash" || input.starts_with("roothash ") {
+                grove::prompt_root_hash(input, &drive, session);
+                true
+            } else if input.starts_with("fingerprint ") {
+                grove::prompt_fingerprint(input, &drive, &contract);
+                true
+            } else if input == "whoami"
+                || input.starts_with("whoami ")
+                || input.starts_with("identity ")
+                || input.starts_with("mine ")
+                || input.starts_with("use ")
+                || input == "contracts"
+            {
+                contract_command(input, drive, contract, contracts, session, rl)
             } else if input == "exit" {
                 false
             } else {
                 true
+            };
+            if mutating {
+                grove::print_root_hash_if_enabled(drive, session);
             }
+            result
         }
         Err(_) => {
             println!("no input, try again");
@@ -485,7 +549,13 @@ fn person_rl(drive: &Drive, contract: &Contract, rl: &mut Editor<()>) -> bool {
     }
 }
 
-pub fn person_loop(drive: &Drive, contract: &Contract, rl: &mut Editor<()>) -> bool {
+pub fn person_loop(
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &mut Session,
+    rl: &mut Editor<()>,
+) -> bool {
     print_person_contract_options();
-    person_rl(drive, contract, rl)
+    person_rl(drive, contract, contracts, session, rl)
 }
@@ -0,0 +1,115 @@
+use crate::contract::credits_in_cents;
+use prettytable::{Cell, Row, Table};
+use rs_drive::fee::epoch::PERPETUAL_STORAGE_EPOCHS as STORAGE_FEE_EPOCHS;
+use rs_drive::fee::op::STORAGE_DISK_USAGE_CREDIT_PER_BYTE as STORAGE_CREDITS_PER_BYTE;
+use std::collections::BTreeMap;
+
+/// Storage fees are paid out to the epochs following the one an item was stored in, the part
+/// not paid out yet when the item is removed goes back to its owner.
+pub fn refunded_credits(bytes: u64, stored_epoch: u16, current_epoch: u16) -> u64 {
+    let paid_epochs = current_epoch
+        .saturating_sub(stored_epoch)
+        .min(STORAGE_FEE_EPOCHS);
+    let unpaid_epochs = u64::from(STORAGE_FEE_EPOCHS - paid_epochs);
+    bytes * STORAGE_CREDITS_PER_BYTE * unpaid_epochs / u64::from(STORAGE_FEE_EPOCHS)
+}
+
+/// Adds bytes stored by epoch to a running total, for refunds of several items.
+pub fn add_epoch_bytes(
+    total: &mut BTreeMap<Option<u16>, u64>,
+    epoch_bytes: &BTreeMap<Option<u16>, u64>,
+) {
+    for (epoch, bytes) in epoch_bytes.iter() {
+        *total.entry(*epoch).or_default() += bytes;
+    }
+}
+
+/// Prints the estimated refund for bytes by the epoch they were stored in, items without
+/// storage flags are counted as stored in epoch 0.
+pub fn print_refunds(epoch_bytes: &BTreeMap<Option<u16>, u64>, current_epoch: u16) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("stored epoch"),
+        Cell::new("bytes"),
+        Cell::new("epochs paid out"),
+        Cell::new("refund"),
+    ]));
+    let mut total_refund = 0;
+    for (epoch, bytes) in epoch_bytes.iter() {
+        let stored_epoch = epoch.unwrap_or_default();
+        let refund = refunded_credits(*bytes, stored_epoch, current_epoch);
+        total_refund += refund;
+        let paid_epochs = current_epoch
+            .saturating_sub(stored_epoch)
+            .min(STORAGE_FEE_EPOCHS);
+        table.add_row(Row::new(vec![
+            Cell::new(
+                epoch
+                    .map_or("none".to_string(), |epoch| epoch.to_string())
+                    .as_str(),
+            ),
+            Cell::new(bytes.to_string().as_str()),
+            Cell::new(format!("{}/{}", paid_epochs, STORAGE_FEE_EPOCHS).as_str()),
+            Cell::new(refund.to_string().as_str()),
+        ]));
+    }
+    table.printstd();
+    println!(
+        "Refunded storage: {} ({:.2}¢), estimated at epoch {} from the bytes of the documents and their index references",
+        total_refund,
+        credits_in_cents(total_refund as f64),
+        current_epoch
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPOCHS: u64 = STORAGE_FEE_EPOCHS as u64;
+
+    fn refund_for_unpaid_epochs(bytes: u64, unpaid_epochs: u64) -> u64 {
+        bytes * STORAGE_CREDITS_PER_BYTE * unpaid_epochs / EPOCHS
+    }
+
+    #[test]
+    fn refunds_everything_in_the_epoch_of_storage() {
+        assert_eq!(refunded_credits(100, 7, 7), 100 * STORAGE_CREDITS_PER_BYTE);
+        // an epoch set back below the stored one has not paid anything out either
+        assert_eq!(refunded_credits(100, 7, 3), 100 * STORAGE_CREDITS_PER_BYTE);
+    }
+
+    #[test]
+    fn refunds_the_epochs_not_paid_out_yet() {
+        assert_eq!(
+            refunded_credits(100, 7, 8),
+            refund_for_unpaid_epochs(100, EPOCHS - 1)
+        );
+        assert_eq!(
+            refunded_credits(100, 0, STORAGE_FEE_EPOCHS / 2),
+            refund_for_unpaid_epochs(100, EPOCHS - EPOCHS / 2)
+        );
+        assert_eq!(
+            refunded_credits(100, 0, STORAGE_FEE_EPOCHS - 1),
+            refund_for_unpaid_epochs(100, 1)
+        );
+    }
+
+    #[test]
+    fn refunds_nothing_once_every_epoch_was_paid() {
+        assert_eq!(refunded_credits(100, 0, STORAGE_FEE_EPOCHS), 0);
+        assert_eq!(refunded_credits(100, 1, STORAGE_FEE_EPOCHS + 5), 0);
+        assert_eq!(refunded_credits(100, 0, u16::MAX), 0);
+    }
+
+    #[test]
+    fn adds_bytes_by_epoch() {
+        let mut total = BTreeMap::new();
+        add_epoch_bytes(&mut total, &BTreeMap::from([(Some(1), 10), (None, 5)]));
+        add_epoch_bytes(&mut total, &BTreeMap::from([(Some(1), 3), (Some(2), 4)]));
+        assert_eq!(
+            total,
+            BTreeMap::from([(None, 5), (Some(1), 13), (Some(2), 4)])
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use crate::contract::{all_documents, decode_id};
+use crate::date::declared_timestamp_fields;
+use crate::display::content_media_types;
+use crate::session::Session;
+use crate::validate::{print_diagnostics, validate_contract_file};
+use indexmap::IndexMap;
+use prettytable::{Cell, Row, Table};
+use rs_drive::common;
+use rs_drive::contract::Contract;
+use rs_drive::drive::Drive;
+use rs_drive::error::contract::ContractError;
+use rs_drive::error::Error;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Copy)]
+pub enum ContractType {
+    PersonContract,
+    DashPayContract,
+    DPNSContract,
+    OtherContract,
+}
+
+impl ContractType {
+    /// Recognizes the DPNS and DashPay contracts by their document types, so that loading them
+    /// from a file also gives their own commands.
+    pub fn from_document_types(contract: &Contract) -> Self {
+        let has = |names: &[&str]| {
+            names
+                .iter()
+                .all(|name| contract.document_types.contains_key(*name))
+        };
+        if has(&["domain", "preorder"]) {
+            ContractType::DPNSContract
+        } else if has(&["profile", "contactRequest"]) {
+            ContractType::DashPayContract
+        } else {
+            ContractType::OtherContract
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ContractType::PersonContract => "person",
+            ContractType::DashPayContract => "dashpay",
+            ContractType::DPNSContract => "dpns",
+            ContractType::OtherContract => "other",
+        }
+    }
+}
+
+pub struct LoadedContract {
+    pub contract_type: ContractType,
+    /// File the contract was loaded from
+    pub path: String,
+    /// Shared so the contract loops can hold it while commands like upgrade change the registry
+    pub contract: Rc<Contract>,
+    /// Content media types by `documentType.property`, Drive does not keep them
+    pub media_types: BTreeMap<String, String>,
+    /// System timestamps set on writes by document type, Drive does not keep them either
+    pub timestamp_fields: BTreeMap<String, Vec<String>>,
+}
+
+impl LoadedContract {
+    pub fn new(contract_type: ContractType, path: &str, contract: Contract) -> Self {
+        LoadedContract {
+            contract_type,
+            path: path.to_string(),
+            contract: Rc::new(contract),
+            media_types: content_media_types(path),
+            timestamp_fields: declared_timestamp_fields(path),
+        }
+    }
+}
+
+/// Contracts loaded during the session by alias, they stay in Drive when switching between them.
+#[derive(Default)]
+pub struct ContractRegistry {
+    contracts: IndexMap<String, LoadedContract>,
+}
+
+impl ContractRegistry {
+    pub fn get(&self, alias: &str) -> Option<&LoadedContract> {
+        self.contracts.get(alias)
+    }
+
+    pub fn alias_for_path(&self, path: &str) -> Option<String> {
+        self.contracts
+            .iter()
+            .find(|(_, loaded)| loaded.path == path)
+            .map(|(alias, _)| alias.clone())
+    }
+
+    /// The wanted alias, suffixed with a number when another contract already uses it.
+    pub fn unused_alias(&self, wanted: &str) -> String {
+        if !self.contracts.contains_key(wanted) {
+            return wanted.to_string();
+        }
+        (2..)
+            .map(|i| format!("{}_{}", wanted, i))
+            .find(|alias| !self.contracts.contains_key(alias))
+            .unwrap()
+    }
+
+    pub fn insert(&mut self, alias: String, loaded: LoadedContract) {
+        self.contracts.insert(alias, loaded);
+    }
+
+    /// Swaps the contract of an alias for a new version loaded from another file.
+    pub fn replace_contract(&mut self, alias: &str, path: &str, contract: Contract) {
+        if let Some(loaded) = self.contracts.get_mut(alias) {
+            loaded.path = path.to_string();
+            loaded.contract = Rc::new(contract);
+            loaded.media_types = content_media_types(path);
+            loaded.timestamp_fields = declared_timestamp_fields(path);
+        }
+    }
+
+    fn aliases(&self) -> Vec<&str> {
+        self.contracts.keys().map(|alias| alias.as_str()).collect()
+    }
+}
+
+/// Reads the `$id` a contract file declares.
+fn contract_file_id(contract_path: &str) -> Option<[u8; 32]> {
+    let data = fs::read_to_string(contract_path).ok()?;
+    let contract = serde_json::from_str::<JsonValue>(data.as_str()).ok()?;
+    decode_id(contract.get("$id")?.as_str()?)
+}
+
+/// Validates a contract file and applies it under the `$id` it declares, so that sessions
+/// loading the same file and running the same commands end up with the same root hash.
+pub fn apply_contract_file(drive: &Drive, contract_path: &str) -> Result<Contract, Error> {
+    let diagnostics = validate_contract_file(contract_path);
+    if !diagnostics.is_empty() {
+        print_diagnostics(&diagnostics);
+        return Err(Error::Contract(ContractError::InvalidContractStructure(
+            "contract did not pass validation",
+        )));
+    }
+    let contract_id = contract_file_id(contract_path).ok_or(Error::Contract(
+        ContractError::InvalidContractStructure("contract should have a 32 byte $id"),
+    ))?;
+    let db_transaction = drive.grove.start_transaction();
+    let contract = common::setup_contract(
+        drive,
+        contract_path,
+        Some(contract_id),
+        Some(&db_transaction),
+    );
+    drive.commit_transaction(db_transaction)?;
+    Ok(contract)
+}
+
+/// Alias derived from the contract file name, usable as an sql identifier.
+pub fn alias_from_path(path: &str) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("contract");
+    let alias: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if alias.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("c_{}", alias)
+    } else {
+        alias
+    }
+}
+
+pub fn prompt_use(input: String, registry: &ContractRegistry, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 {
+        println!("### ERROR! Two parameter should be provided");
+        return;
+    }
+    let alias = args.get(1).unwrap();
+    if registry.get(alias).is_some() {
+        session.current_contract = Some(alias.to_string());
+        println!("Using contract {}", alias);
+    } else {
+        println!("### ERROR! No contract is loaded as {}", alias);
+        println!("### Loaded contracts: {}", registry.aliases().join(", "));
+    }
+}
+
+pub fn print_contracts(drive: &Drive, registry: &ContractRegistry, session: &Session) {
+    if registry.contracts.is_empty() {
+        println!("No contracts loaded");
+        return;
+    }
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new(""),
+        Cell::new("alias"),
+        Cell::new("type"),
+        Cell::new("id"),
+        Cell::new("documents"),
+        Cell::new("path"),
+    ]));
+    for (alias, loaded) in registry.contracts.iter() {
+        let current = if session.current_contract.as_deref() == Some(alias.as_str()) {
+            "*"
+        } else {
+            ""
+        };
+        let counts = loaded
+            .contract
+            .document_types
+            .iter()
+            .map(|(name, document_type)| {
+                match all_documents(drive, &loaded.contract, document_type) {
+                    Ok(documents) => format!("{}: {}", name, documents.len()),
+                    Err(_) => format!("{}: ?", name),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        table.add_row(Row::new(vec![
+            Cell::new(current),
+            Cell::new(alias.as_str()),
+            Cell::new(loaded.contract_type.name()),
+            Cell::new(bs58::encode(loaded.contract.id).into_string().as_str()),
+            Cell::new(counts.as_str()),
+            Cell::new(loaded.path.as_str()),
+        ]));
+    }
+    table.printstd();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::populate_with_documents;
+    use crate::grove::{document_type_hash, root_hash};
+    use crate::session::prompt_seed;
+    use tempdir::TempDir;
+
+    const PERSON_CONTRACT_PATH: &str = "src/supporting_files/contract/family/family-contract.json";
+
+    /// Loads the person contract in a new Drive and populates it the way `seed 7` followed by
+    /// `pop person 20` does.
+    fn fingerprints_of_new_session(name: &str) -> ([u8; 32], [u8; 32], Option<[u8; 32]>) {
+        let tmp_dir = TempDir::new(name).unwrap();
+        let drive = Drive::open(&tmp_dir).expect("expected to open Drive successfully");
+        drive.create_root_tree(None);
+        let contract = apply_contract_file(&drive, PERSON_CONTRACT_PATH)
+            .expect("expected to apply the contract");
+        let mut session = Session::default();
+        prompt_seed("seed 7".to_string(), &mut session);
+        let document_type = contract.document_type_for_name("person").unwrap();
+        let documents = document_type.random_documents(20, session.next_seed());
+        populate_with_documents(documents, &drive, document_type, &contract, true, &session)
+            .expect("expected to insert the documents");
+        (
+            contract.id,
+            document_type_hash(&drive, &contract, "person").expect("expected a person subtree"),
+            root_hash(&drive).expect("expected a root hash"),
+        )
+    }
+
+    #[test]
+    fn loading_the_same_contract_twice_gives_the_same_fingerprints() {
+        let first = fingerprints_of_new_session("first");
+        let second = fingerprints_of_new_session("second");
+        assert_eq!(
+            bs58::encode(first.0).into_string(),
+            "94zNLp7A1ZcYG3Egqf2YmQk4DQr9P8D543GwXyCJRz4"
+        );
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::contract::decode_id;
+use crate::date::{date_string, parse_date, parse_duration, timestamp_seconds};
+use crate::display::DisplaySettings;
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rs_drive::contract::{document::Document, DocumentType};
+
+/// State kept for the whole explorer session, independently of the loaded contract.
+#[derive(Default)]
+pub struct Session {
+    /// Alias of the contract commands apply to, none while in the base menu
+    pub current_contract: Option<String>,
+    /// Identity used as the owner of inserted, updated and deleted documents
+    pub owner_id: Option<[u8; 32]>,
+    /// Current path of the GroveDB tree browser
+    pub tree_path: Vec<Vec<u8>>,
+    /// Show the root hash after every command changing the state
+    pub show_root_hash: bool,
+    /// Sql queries kept to check which ones the contract indices can serve
+    pub saved_queries: Vec<String>,
+    /// How values are rendered in results
+    pub display: DisplaySettings,
+    /// Documents of the last result table, rows can be expanded with show
+    pub last_results: Option<(DocumentType, Vec<Document>)>,
+    /// Time of the simulated chain, the wall clock is used when it is not set
+    pub clock: Option<DateTime<Utc>>,
+    /// Epoch written in the storage flags of new items
+    pub epoch: u16,
+    /// Seed of the ids and values of generated items, entropy is used when it is not set
+    pub seed: Option<u64>,
+    /// Generators handed out since the seed was set, each one is seeded differently
+    seeded_generators: u64,
+}
+
+impl Session {
+    /// The session owner, or a random one when no identity was chosen.
+    pub fn owner_id_or_random(&mut self) -> [u8; 32] {
+        match self.owner_id {
+            Some(owner_id) => owner_id,
+            None => self.rng().gen::<[u8; 32]>(),
+        }
+    }
+
+    /// Seed for the next generator of ids or random items, replaying the same commands after
+    /// the same `seed` generates the same items. None when no seed was set.
+    pub fn next_seed(&mut self) -> Option<u64> {
+        let seed = self.seed?;
+        self.seeded_generators += 1;
+        Some(seed.wrapping_add(self.seeded_generators))
+    }
+
+    /// Generator of ids and random values, seeded when the session has a seed.
+    pub fn rng(&mut self) -> StdRng {
+        match self.next_seed() {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    pub fn owner_id_slice(&self) -> Option<&[u8]> {
+        self.owner_id.as_ref().map(|owner_id| owner_id.as_slice())
+    }
+
+    /// Time used for document timestamps and relative dates.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.unwrap_or_else(Utc::now)
+    }
+
+    /// Block time given to Drive for queries and writes, only once the clock was set.
+    pub fn block_time(&self) -> Option<f64> {
+        self.clock.map(timestamp_seconds)
+    }
+}
+
+fn print_owner(session: &Session) {
+    match session.owner_id {
+        None => println!("No identity set, documents get a random owner"),
+        Some(owner_id) => println!("Identity: {}", bs58::encode(owner_id).into_string()),
+    }
+}
+
+pub fn prompt_whoami(input: String, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    match args.len() {
+        1 => print_owner(session),
+        2 => {
+            let identity = args.get(1).unwrap();
+            if *identity == "none" {
+                session.owner_id = None;
+                print_owner(session);
+            } else {
+                match decode_id(identity) {
+                    Some(owner_id) => {
+                        session.owner_id = Some(owner_id);
+                        print_owner(session);
+                    }
+                    None => println!("### ERROR! Could not decode identity id"),
+                }
+            }
+        }
+        _ => println!("### ERROR! At max one parameter should be provided"),
+    }
+}
+
+pub fn prompt_identity(input: String, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 || args.get(1) != Some(&"new") {
+        println!("### ERROR! Only 'identity new' is supported");
+    } else {
+        session.owner_id = Some(session.rng().gen::<[u8; 32]>());
+        print_owner(session);
+    }
+}
+
+fn print_seed(session: &Session) {
+    match session.seed {
+        None => println!("Seed: none, generated ids and items are random"),
+        Some(seed) => println!("Seed: {}", seed),
+    }
+}
+
+pub fn prompt_seed(input: String, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    match args.len() {
+        1 => print_seed(session),
+        2 => {
+            let seed = args.get(1).unwrap();
+            if *seed == "off" {
+                session.seed = None;
+                print_seed(session);
+            } else {
+                match seed.parse::<u64>() {
+                    Ok(seed) => {
+                        session.seed = Some(seed);
+                        session.seeded_generators = 0;
+                        print_seed(session);
+                    }
+                    Err(_) => println!("### ERROR! The seed should be an integer or off"),
+                }
+            }
+        }
+        _ => println!("### ERROR! At max one parameter should be provided"),
+    }
+}
+
+fn print_clock(session: &Session) {
+    match session.clock {
+        None => println!("Clock: real time, queries have no block time"),
+        Some(clock) => println!(
+            "Clock: {} (block time {})",
+            date_string(clock, session.display.timezone),
+            timestamp_seconds(clock)
+        ),
+    }
+}
+
+pub fn prompt_clock(input: String, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    match (args.get(1), args.get(2)) {
+        (None, _) => print_clock(session),
+        (Some(&"set"), Some(_)) => {
+            let time = args[2..].join(" ");
+            match parse_date(time.as_str(), session.now(), session.display.timezone) {
+                Ok(clock) => {
+                    session.clock = Some(clock);
+                    print_clock(session);
+                }
+                Err(e) => {
+                    println!("### ERROR! Invalid time");
+                    println!("### Info {}", e);
+                }
+            }
+        }
+        (Some(&"advance"), Some(duration)) => match parse_duration(duration)
+            .and_then(|duration| session.now().checked_add_signed(duration))
+        {
+            Some(clock) => {
+                session.clock = Some(clock);
+                print_clock(session);
+            }
+            None => println!("### ERROR! The duration should be like 30s, 15m, 1h, 2d or 1w"),
+        },
+        (Some(&"real"), None) => {
+            session.clock = None;
+            print_clock(session);
+        }
+        _ => {
+            println!("### ERROR! Use clock set <iso time>, clock advance <duration> or clock real")
+        }
+    }
+}
+
+pub fn prompt_epoch(input: String, session: &mut Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    match args.len() {
+        1 => println!("Epoch: {}", session.epoch),
+        2 => match args.get(1).unwrap().parse::<u16>() {
+            Ok(epoch) => {
+                session.epoch = epoch;
+                println!("Epoch: {}", session.epoch);
+            }
+            Err(_) => println!("### ERROR! The epoch should be an integer up to {}", u16::MAX),
+        },
+        _ => println!("### ERROR! At max one parameter should be provided"),
+    }
+}
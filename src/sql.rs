@@ -0,0 +1,414 @@
+use sqlparser::ast::{Expr, SelectItem, SetExpr, Statement, TableFactor, Value};
+use sqlparser::dialect::MySqlDialect;
+use sqlparser::parser::Parser;
+
+#[derive(Clone, Copy)]
+pub enum AggregateFunction {
+    Count,
+    Min,
+    Max,
+    Sum,
+    Avg,
+}
+
+impl AggregateFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(AggregateFunction::Count),
+            "min" => Some(AggregateFunction::Min),
+            "max" => Some(AggregateFunction::Max),
+            "sum" => Some(AggregateFunction::Sum),
+            "avg" => Some(AggregateFunction::Avg),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "count",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Avg => "avg",
+        }
+    }
+}
+
+pub enum SelectColumn {
+    Column(String),
+    /// An aggregate over a column, or over whole documents for `count(*)`
+    Aggregate(AggregateFunction, Option<String>),
+}
+
+impl SelectColumn {
+    pub fn header(&self) -> String {
+        match self {
+            SelectColumn::Column(column) => column.clone(),
+            SelectColumn::Aggregate(function, column) => {
+                format!("{}({})", function.name(), column.as_deref().unwrap_or("*"))
+            }
+        }
+    }
+}
+
+/// What a select shows of the matching documents.
+pub enum Projection {
+    /// `*`, every column
+    All,
+    /// Properties or system fields like `$id`, nested properties use dotted paths
+    Columns(Vec<String>),
+    /// Aggregates Drive can not compute, evaluated by the explorer over the matching documents
+    Aggregates {
+        columns: Vec<SelectColumn>,
+        group_by: Vec<String>,
+    },
+}
+
+pub struct Select {
+    /// Alias of the contract named by `from alias.documentType`
+    pub contract_alias: Option<String>,
+    pub document_type_name: String,
+    /// Query given to Drive, selecting everything from the document type alone
+    pub drive_sql: String,
+    pub projection: Projection,
+    /// The query sets its own limit, so aggregates are not computed page after page
+    pub has_limit: bool,
+}
+
+fn column_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier(ident) => Some(ident.value.clone()),
+        Expr::CompoundIdentifier(idents) => Some(
+            idents
+                .iter()
+                .map(|ident| ident.value.as_str())
+                .collect::<Vec<&str>>()
+                .join("."),
+        ),
+        _ => None,
+    }
+}
+
+fn select_column(expr: &Expr) -> Result<SelectColumn, String> {
+    if let Expr::Function(function) = expr {
+        let aggregate_function = AggregateFunction::from_name(function.name.to_string().as_str())
+            .ok_or(format!("{} is not supported", expr))?;
+        let column = match function.args.as_slice() {
+            [argument] if argument.to_string() == "*" => None,
+            [argument] => Some(argument.to_string()),
+            _ => return Err(format!("{} should have one argument", expr)),
+        };
+        return match (aggregate_function, column) {
+            (AggregateFunction::Count, None) => {
+                Ok(SelectColumn::Aggregate(aggregate_function, None))
+            }
+            (_, None) => Err(format!("{} needs a column", expr)),
+            (_, column) => Ok(SelectColumn::Aggregate(aggregate_function, column)),
+        };
+    }
+    column_name(expr)
+        .map(SelectColumn::Column)
+        .ok_or(format!("{} can not be selected", expr))
+}
+
+fn projection(items: &[SelectItem], group_by: &[Expr]) -> Result<Projection, String> {
+    let group_by = group_by
+        .iter()
+        .map(|expr| column_name(expr).ok_or(format!("can not group by {}", expr)))
+        .collect::<Result<Vec<String>, String>>()?;
+    let mut columns = vec![];
+    for item in items.iter() {
+        let expr = match item {
+            SelectItem::Wildcard if group_by.is_empty() => return Ok(Projection::All),
+            SelectItem::UnnamedExpr(expr) => expr,
+            SelectItem::ExprWithAlias { expr, .. } => expr,
+            _ => return Err(format!("{} can not be selected", item)),
+        };
+        columns.push(select_column(expr)?);
+    }
+    let aggregated = columns
+        .iter()
+        .any(|column| matches!(column, SelectColumn::Aggregate(..)));
+    if !aggregated && group_by.is_empty() {
+        let columns = columns
+            .into_iter()
+            .filter_map(|column| match column {
+                SelectColumn::Column(column) => Some(column),
+                SelectColumn::Aggregate(..) => None,
+            })
+            .collect();
+        return Ok(Projection::Columns(columns));
+    }
+    for column in columns.iter() {
+        if let SelectColumn::Column(column) = column {
+            if !group_by.contains(column) {
+                return Err(format!(
+                    "{} should be aggregated or in the group by",
+                    column
+                ));
+            }
+        }
+    }
+    Ok(Projection::Aggregates { columns, group_by })
+}
+
+/// Parses a select, keeping for the explorer what Drive does not handle: the contract alias in
+/// `from alias.documentType` and the projection.
+pub fn parse_select(sql: &str) -> Result<Select, String> {
+    let mut statements = Parser::parse_sql(&MySqlDialect {}, sql).map_err(|e| e.to_string())?;
+    if statements.len() != 1 {
+        return Err("exactly one statement should be provided".to_string());
+    }
+    let mut statement = statements.remove(0);
+    let query = match &mut statement {
+        Statement::Query(query) => query,
+        _ => return Err("only select queries are supported".to_string()),
+    };
+    let has_limit = query.limit.is_some();
+    let select = match &mut query.body {
+        SetExpr::Select(select) => select,
+        _ => return Err("only select queries are supported".to_string()),
+    };
+    let (contract_alias, document_type_name) = match select.from.first_mut() {
+        Some(table) => match &mut table.relation {
+            TableFactor::Table { name, .. } => {
+                let contract_alias = match name.0.len() {
+                    2 => Some(name.0.remove(0).value),
+                    _ => None,
+                };
+                let document_type_name = name.to_string();
+                (contract_alias, document_type_name)
+            }
+            _ => return Err("documents should be selected from a document type".to_string()),
+        },
+        None => return Err("a document type should be provided with from".to_string()),
+    };
+    let projection = projection(&select.projection, &select.group_by)?;
+    let drive_sql = match (&projection, &contract_alias) {
+        (Projection::All, None) => sql.to_string(),
+        _ => {
+            select.projection = vec![SelectItem::Wildcard];
+            select.group_by = vec![];
+            statement.to_string()
+        }
+    };
+    Ok(Select {
+        contract_alias,
+        document_type_name,
+        drive_sql,
+        projection,
+        has_limit,
+    })
+}
+
+fn resolve_literal(
+    expr: &mut Expr,
+    resolve: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<(), String> {
+    if let Expr::Value(Value::SingleQuotedString(text)) = expr {
+        *expr = Expr::Value(Value::Number(resolve(text.as_str())?, false));
+    }
+    Ok(())
+}
+
+fn is_column_in(expr: &Expr, columns: &[String]) -> bool {
+    column_name(expr).map_or(false, |column| columns.contains(&column))
+}
+
+fn resolve_expr(
+    expr: &mut Expr,
+    columns: &[String],
+    resolve: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<(), String> {
+    match expr {
+        Expr::BinaryOp { left, right, .. } if is_column_in(left, columns) => {
+            resolve_literal(right, resolve)
+        }
+        Expr::BinaryOp { left, right, .. } => {
+            resolve_expr(left, columns, resolve)?;
+            resolve_expr(right, columns, resolve)
+        }
+        Expr::Between {
+            expr, low, high, ..
+        } if is_column_in(expr, columns) => {
+            resolve_literal(low, resolve)?;
+            resolve_literal(high, resolve)
+        }
+        Expr::InList { expr, list, .. } if is_column_in(expr, columns) => list
+            .iter_mut()
+            .try_for_each(|item| resolve_literal(item, resolve)),
+        Expr::Nested(expr) => resolve_expr(expr, columns, resolve),
+        _ => Ok(()),
+    }
+}
+
+/// Replaces the quoted strings compared to `columns` in the where clause by the numbers
+/// `resolve` reads from them, so dates can be written as text for Drive to compare as numbers.
+pub fn resolve_literals(
+    drive_sql: &str,
+    columns: &[String],
+    resolve: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut statements =
+        Parser::parse_sql(&MySqlDialect {}, drive_sql).map_err(|e| e.to_string())?;
+    let selection = match statements.first_mut() {
+        Some(Statement::Query(query)) => match &mut query.body {
+            SetExpr::Select(select) => &mut select.selection,
+            _ => return Ok(drive_sql.to_string()),
+        },
+        _ => return Ok(drive_sql.to_string()),
+    };
+    let original = selection.as_ref().map(|selection| selection.to_string());
+    if let Some(expr) = selection.as_mut() {
+        resolve_expr(expr, columns, resolve)?;
+    }
+    if selection.as_ref().map(|selection| selection.to_string()) == original {
+        return Ok(drive_sql.to_string());
+    }
+    Ok(statements[0].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(columns: &[SelectColumn]) -> Vec<String> {
+        columns.iter().map(|column| column.header()).collect()
+    }
+
+    #[test]
+    fn keeps_a_plain_select_for_drive() {
+        let sql = "select * from person where age > 3";
+        let select = parse_select(sql).unwrap();
+        assert!(matches!(select.projection, Projection::All));
+        assert_eq!(select.contract_alias, None);
+        assert_eq!(select.document_type_name, "person");
+        assert_eq!(select.drive_sql, sql);
+        assert!(!select.has_limit);
+    }
+
+    #[test]
+    fn takes_the_contract_alias_out_of_the_drive_query() {
+        let select = parse_select("select * from dpns.domain limit 5").unwrap();
+        assert_eq!(select.contract_alias.as_deref(), Some("dpns"));
+        assert_eq!(select.document_type_name, "domain");
+        assert!(!select.drive_sql.contains("dpns"));
+        assert!(select.drive_sql.to_lowercase().contains("from domain"));
+        assert!(select.has_limit);
+    }
+
+    #[test]
+    fn selects_columns() {
+        let select = parse_select("select firstName, records.dashAliasIdentityId from person")
+            .unwrap();
+        match select.projection {
+            Projection::Columns(columns) => {
+                assert_eq!(columns, vec!["firstName", "records.dashAliasIdentityId"])
+            }
+            _ => panic!("expected columns"),
+        }
+        assert!(select.drive_sql.to_lowercase().starts_with("select * from person"));
+    }
+
+    #[test]
+    fn reads_aggregates_with_aliases_and_group_by() {
+        let select = parse_select(
+            "select firstName, count(*) as people, avg(age) from person group by firstName",
+        )
+        .unwrap();
+        match &select.projection {
+            Projection::Aggregates { columns, group_by } => {
+                assert_eq!(headers(columns), vec!["firstName", "count(*)", "avg(age)"]);
+                assert_eq!(group_by, &vec!["firstName".to_string()]);
+            }
+            _ => panic!("expected aggregates"),
+        }
+        let drive_sql = select.drive_sql.to_lowercase();
+        assert!(drive_sql.starts_with("select * from person"));
+        assert!(!drive_sql.contains("group by"));
+        assert!(!select.has_limit);
+    }
+
+    #[test]
+    fn reads_aggregates_of_an_aliased_contract() {
+        let select = parse_select("select min(age), max(age), sum(age) from family.person")
+            .unwrap();
+        assert_eq!(select.contract_alias.as_deref(), Some("family"));
+        match &select.projection {
+            Projection::Aggregates { columns, group_by } => {
+                assert_eq!(headers(columns), vec!["min(age)", "max(age)", "sum(age)"]);
+                assert!(group_by.is_empty());
+            }
+            _ => panic!("expected aggregates"),
+        }
+        assert!(!select.drive_sql.contains("family"));
+    }
+
+    #[test]
+    fn rejects_invalid_projections() {
+        assert!(parse_select("select age from person group by firstName").is_err());
+        assert!(parse_select("select median(age) from person").is_err());
+        assert!(parse_select("select sum(*) from person").is_err());
+        assert!(parse_select("select count(age, firstName) from person").is_err());
+        assert!(parse_select("select 1").is_err());
+        assert!(parse_select("delete from person").is_err());
+    }
+
+    fn resolve_day(text: &str) -> Result<String, String> {
+        match text {
+            "2024-01-01" => Ok("1704067200".to_string()),
+            _ => Err(format!("{} is not a date", text)),
+        }
+    }
+
+    #[test]
+    fn resolves_literals_compared_to_date_columns() {
+        let columns = vec!["createdAt".to_string()];
+        let sql = resolve_literals(
+            "select * from note where createdAt > '2024-01-01' and title = '2024-01-01'",
+            &columns,
+            &resolve_day,
+        )
+        .unwrap();
+        assert!(sql.contains("createdAt > 1704067200"));
+        assert!(sql.contains("title = '2024-01-01'"));
+
+        let sql = resolve_literals(
+            "select * from note where createdAt between '2024-01-01' and '2024-01-01'",
+            &columns,
+            &resolve_day,
+        )
+        .unwrap();
+        assert!(sql.contains("1704067200 AND 1704067200"));
+
+        let sql = resolve_literals(
+            "select * from note where (createdAt in ('2024-01-01'))",
+            &columns,
+            &resolve_day,
+        )
+        .unwrap();
+        assert!(sql.contains("(1704067200)"));
+    }
+
+    #[test]
+    fn keeps_queries_without_date_literals() {
+        let columns = vec!["createdAt".to_string()];
+        let sql = "select * from note where title = 'draft' limit 5";
+        assert_eq!(resolve_literals(sql, &columns, &resolve_day).unwrap(), sql);
+        let sql = "select * from note where createdAt > 1704067200";
+        assert_eq!(resolve_literals(sql, &columns, &resolve_day).unwrap(), sql);
+    }
+
+    #[test]
+    fn reports_unreadable_date_literals() {
+        let columns = vec!["createdAt".to_string()];
+        assert_eq!(
+            resolve_literals(
+                "select * from note where createdAt < 'tomorrow'",
+                &columns,
+                &resolve_day,
+            ),
+            Err("tomorrow is not a date".to_string())
+        );
+    }
+}
@@ -0,0 +1,675 @@
+use crate::contract::{all_documents, confirm, print_fees};
+use crate::registry::ContractRegistry;
+use crate::session::Session;
+use crate::validate::{pointer, print_diagnostics, validate_contract_file, SYSTEM_FIELDS};
+use ciborium::value::Value;
+use rs_drive::common;
+use rs_drive::contract::types::DocumentFieldType;
+use rs_drive::contract::{document::Document, Contract, DocumentType};
+use rs_drive::drive::Drive;
+use rustyline::Editor;
+use serde_json::{Map, Value as JsonValue};
+use std::fs;
+
+/// How many invalid documents are shown for each document type after an upgrade
+const SHOWN_VIOLATIONS: usize = 5;
+
+/// Bounds that break stored documents when lowered
+const UPPER_BOUNDS: [&str; 3] = ["maxLength", "maxItems", "maximum"];
+
+/// Bounds that break stored documents when raised
+const LOWER_BOUNDS: [&str; 3] = ["minLength", "minItems", "minimum"];
+
+pub struct ContractChange {
+    /// JSON pointer to the changed value in the new contract, or the old one when removed
+    pub pointer: String,
+    pub description: String,
+    /// Stored documents or queries written for the old contract may stop working
+    pub breaking: bool,
+}
+
+fn change(segments: &[&str], description: String, breaking: bool) -> ContractChange {
+    ContractChange {
+        pointer: pointer(segments),
+        description,
+        breaking,
+    }
+}
+
+fn read_contract_json(path: &str) -> Result<JsonValue, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    serde_json::from_str(data.as_str()).map_err(|e| format!("malformed json in {}: {}", path, e))
+}
+
+fn object<'a>(value: &'a JsonValue, key: &str) -> Option<&'a Map<String, JsonValue>> {
+    value.get(key).and_then(|value| value.as_object())
+}
+
+fn required_names(schema: &JsonValue) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(|required| required.as_array())
+        .map(|required| required.iter().filter_map(|name| name.as_str()).collect())
+        .unwrap_or_default()
+}
+
+fn diff_bounds(
+    segments: &[&str],
+    old: &JsonValue,
+    new: &JsonValue,
+    changes: &mut Vec<ContractChange>,
+) {
+    for (bound, is_upper) in UPPER_BOUNDS
+        .iter()
+        .map(|bound| (bound, true))
+        .chain(LOWER_BOUNDS.iter().map(|bound| (bound, false)))
+    {
+        let old_bound = old.get(*bound).and_then(|bound| bound.as_f64());
+        let new_bound = new.get(*bound).and_then(|bound| bound.as_f64());
+        let bound_segments = [segments, &[*bound]].concat();
+        match (old_bound, new_bound) {
+            (None, None) => {}
+            (Some(old_bound), Some(new_bound)) if old_bound == new_bound => {}
+            (Some(old_bound), Some(new_bound)) => changes.push(change(
+                &bound_segments,
+                format!("{} changed from {} to {}", bound, old_bound, new_bound),
+                (new_bound < old_bound) == is_upper,
+            )),
+            (None, Some(new_bound)) => changes.push(change(
+                &bound_segments,
+                format!("{} set to {}", bound, new_bound),
+                true,
+            )),
+            (Some(old_bound), None) => changes.push(change(
+                &bound_segments,
+                format!("{} of {} removed", bound, old_bound),
+                false,
+            )),
+        }
+    }
+}
+
+fn diff_property(
+    segments: &[&str],
+    old: &JsonValue,
+    new: &JsonValue,
+    changes: &mut Vec<ContractChange>,
+) {
+    let old_type = old.get("type");
+    let new_type = new.get("type");
+    if old_type != new_type || old.get("byteArray") != new.get("byteArray") {
+        changes.push(change(
+            segments,
+            format!(
+                "type changed from {} to {}",
+                old_type.unwrap_or(&JsonValue::Null),
+                new_type.unwrap_or(&JsonValue::Null)
+            ),
+            true,
+        ));
+        return;
+    }
+    diff_bounds(segments, old, new, changes);
+    if new_type == Some(&JsonValue::String("object".to_string())) {
+        diff_properties(segments, old, new, changes);
+    }
+}
+
+/// Compares the properties and required fields of two object schemas.
+fn diff_properties(
+    segments: &[&str],
+    old: &JsonValue,
+    new: &JsonValue,
+    changes: &mut Vec<ContractChange>,
+) {
+    let empty = Map::new();
+    let old_properties = object(old, "properties").unwrap_or(&empty);
+    let new_properties = object(new, "properties").unwrap_or(&empty);
+    let old_required = required_names(old);
+    let new_required = required_names(new);
+
+    for (name, old_property) in old_properties.iter() {
+        let property_segments = [segments, &["properties", name.as_str()]].concat();
+        match new_properties.get(name) {
+            None => changes.push(change(
+                &property_segments,
+                "property removed".to_string(),
+                true,
+            )),
+            Some(new_property) => {
+                diff_property(&property_segments, old_property, new_property, changes)
+            }
+        }
+    }
+    for name in new_properties.keys() {
+        if !old_properties.contains_key(name) {
+            let required = new_required.contains(&name.as_str());
+            changes.push(change(
+                &[segments, &["properties", name.as_str()]].concat(),
+                if required {
+                    "required property added".to_string()
+                } else {
+                    "optional property added".to_string()
+                },
+                required,
+            ));
+        }
+    }
+    // system fields are not in the properties but stored documents may still lack timestamps
+    let is_defined = |properties: &Map<String, JsonValue>, name: &str| {
+        properties.contains_key(name) || SYSTEM_FIELDS.contains(&name)
+    };
+    for name in new_required.iter() {
+        if !old_required.contains(name) && is_defined(old_properties, *name) {
+            changes.push(change(
+                &[segments, &["required"]].concat(),
+                format!("{} became required", name),
+                true,
+            ));
+        }
+    }
+    for name in old_required.iter() {
+        if !new_required.contains(name) && is_defined(new_properties, *name) {
+            changes.push(change(
+                &[segments, &["required"]].concat(),
+                format!("{} is no longer required", name),
+                false,
+            ));
+        }
+    }
+}
+
+/// Indices are told apart by their properties and directions, like `a asc, b desc`.
+fn index_key(index: &JsonValue) -> String {
+    index
+        .get("properties")
+        .and_then(|properties| properties.as_array())
+        .map(|properties| {
+            properties
+                .iter()
+                .filter_map(|property| property.as_object())
+                .flat_map(|property| property.iter())
+                .map(|(name, direction)| format!("{} {}", name, direction.as_str().unwrap_or("?")))
+                .collect::<Vec<String>>()
+                .join(", ")
+        })
+        .unwrap_or_default()
+}
+
+fn is_unique(index: &JsonValue) -> bool {
+    index.get("unique") == Some(&JsonValue::Bool(true))
+}
+
+fn indices(document_type: &JsonValue) -> Vec<(usize, String, &JsonValue)> {
+    document_type
+        .get("indices")
+        .and_then(|indices| indices.as_array())
+        .map(|indices| {
+            indices
+                .iter()
+                .enumerate()
+                .map(|(i, index)| (i, index_key(index), index))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn diff_indices(
+    segments: &[&str],
+    old: &JsonValue,
+    new: &JsonValue,
+    changes: &mut Vec<ContractChange>,
+) {
+    let old_indices = indices(old);
+    let new_indices = indices(new);
+    for (i, key, old_index) in old_indices.iter() {
+        let i = i.to_string();
+        match new_indices.iter().find(|(_, new_key, _)| new_key == key) {
+            None => changes.push(change(
+                &[segments, &["indices", i.as_str()]].concat(),
+                format!("index ({}) removed, queries using it stop working", key),
+                true,
+            )),
+            Some((j, _, new_index)) => {
+                let j = j.to_string();
+                let index_segments = [segments, &["indices", j.as_str(), "unique"]].concat();
+                match (is_unique(old_index), is_unique(new_index)) {
+                    (false, true) => changes.push(change(
+                        &index_segments,
+                        format!("index ({}) became unique", key),
+                        true,
+                    )),
+                    (true, false) => changes.push(change(
+                        &index_segments,
+                        format!("index ({}) is no longer unique", key),
+                        false,
+                    )),
+                    _ => {}
+                }
+            }
+        }
+    }
+    for (j, key, _) in new_indices.iter() {
+        if !old_indices.iter().any(|(_, old_key, _)| old_key == key) {
+            let j = j.to_string();
+            changes.push(change(
+                &[segments, &["indices", j.as_str()]].concat(),
+                format!(
+                    "index ({}) added, documents already stored are not in it",
+                    key
+                ),
+                true,
+            ));
+        }
+    }
+}
+
+/// Lists the changes between two contract json schemas and whether documents and queries made
+/// for the old one keep working with the new one.
+pub fn diff_contracts(old: &JsonValue, new: &JsonValue) -> Vec<ContractChange> {
+    let mut changes = vec![];
+    let empty = Map::new();
+    let old_documents = object(old, "documents").unwrap_or(&empty);
+    let new_documents = object(new, "documents").unwrap_or(&empty);
+    for (name, old_document_type) in old_documents.iter() {
+        let segments = ["documents", name.as_str()];
+        match new_documents.get(name) {
+            None => changes.push(change(&segments, "document type removed".to_string(), true)),
+            Some(new_document_type) => {
+                for setting in ["documentsMutable", "documentsKeepHistory"] {
+                    if old_document_type.get(setting) != new_document_type.get(setting) {
+                        changes.push(change(
+                            &[&segments[..], &[setting]].concat(),
+                            format!("{} changed", setting),
+                            true,
+                        ));
+                    }
+                }
+                diff_properties(
+                    &segments,
+                    old_document_type,
+                    new_document_type,
+                    &mut changes,
+                );
+                diff_indices(
+                    &segments,
+                    old_document_type,
+                    new_document_type,
+                    &mut changes,
+                );
+            }
+        }
+    }
+    for name in new_documents.keys() {
+        if !old_documents.contains_key(name) {
+            changes.push(change(
+                &["documents", name.as_str()],
+                "document type added".to_string(),
+                false,
+            ));
+        }
+    }
+    changes
+}
+
+fn print_changes(changes: &[ContractChange]) {
+    if changes.is_empty() {
+        println!("No schema changes");
+        return;
+    }
+    for change in changes.iter() {
+        let kind = if change.breaking {
+            "breaking"
+        } else {
+            "compatible"
+        };
+        println!("[{}] {} : {}", kind, change.pointer, change.description);
+    }
+    let breaking = changes.iter().filter(|change| change.breaking).count();
+    println!("{} changes, {} breaking", changes.len(), breaking);
+}
+
+pub fn prompt_diff_contract(input: String) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 3 {
+        println!("### ERROR! An old and a new contract path should be provided");
+        return;
+    }
+    let old = read_contract_json(args.get(1).unwrap());
+    let new = read_contract_json(args.get(2).unwrap());
+    match (old, new) {
+        (Ok(old), Ok(new)) => print_changes(&diff_contracts(&old, &new)),
+        (Err(e), _) | (_, Err(e)) => println!("### ERROR! {}", e),
+    }
+}
+
+/// Checks a value against the type a document type gives the property.
+fn value_violation(field_type: &DocumentFieldType, value: &Value) -> Option<String> {
+    match (field_type, value) {
+        (DocumentFieldType::String(min, max), Value::Text(text)) => {
+            let len = text.chars().count();
+            if min.map_or(false, |min| len < min) {
+                Some(format!("is shorter than {} characters", min.unwrap()))
+            } else if max.map_or(false, |max| len > max) {
+                Some(format!("is longer than {} characters", max.unwrap()))
+            } else {
+                None
+            }
+        }
+        (DocumentFieldType::ByteArray(min, max), Value::Bytes(bytes)) => {
+            if min.map_or(false, |min| bytes.len() < min) {
+                Some(format!("has less than {} bytes", min.unwrap()))
+            } else if max.map_or(false, |max| bytes.len() > max) {
+                Some(format!("has more than {} bytes", max.unwrap()))
+            } else {
+                None
+            }
+        }
+        (DocumentFieldType::Integer, Value::Integer(_))
+        | (DocumentFieldType::Number, Value::Integer(_))
+        | (DocumentFieldType::Number, Value::Float(_))
+        | (DocumentFieldType::Date, Value::Integer(_))
+        | (DocumentFieldType::Date, Value::Float(_))
+        | (DocumentFieldType::Boolean, Value::Bool(_))
+        | (DocumentFieldType::Object(_), Value::Map(_))
+        | (DocumentFieldType::Array(_), Value::Array(_)) => None,
+        _ => Some("has the wrong type".to_string()),
+    }
+}
+
+/// Lists why a stored document does not fit a document type.
+fn document_violations(document: &Document, document_type: &DocumentType) -> Vec<String> {
+    let mut violations = vec![];
+    for (name, field) in document_type.properties.iter() {
+        match document.properties.get(name) {
+            None | Some(Value::Null) if field.required => {
+                violations.push(format!("{} is required", name))
+            }
+            None | Some(Value::Null) => {}
+            Some(value) => {
+                if let Some(violation) = value_violation(&field.document_type, value) {
+                    violations.push(format!("{} {}", name, violation));
+                }
+            }
+        }
+    }
+    for name in document.properties.keys() {
+        if !name.starts_with('$') && !document_type.properties.contains_key(name) {
+            violations.push(format!("{} is not defined", name));
+        }
+    }
+    violations
+}
+
+fn revalidate_documents(drive: &Drive, old_contract: &Contract, new_contract: &Contract) {
+    for (name, document_type) in new_contract.document_types.iter() {
+        if !old_contract.document_types.contains_key(name) {
+            continue;
+        }
+        let documents = match all_documents(drive, new_contract, document_type) {
+            Ok(documents) => documents,
+            Err(e) => {
+                println!("### ERROR! Could not fetch {} documents", name);
+                println!("### Info {:?}", e);
+                continue;
+            }
+        };
+        let invalid: Vec<(&Document, Vec<String>)> = documents
+            .iter()
+            .map(|document| (document, document_violations(document, document_type)))
+            .filter(|(_, violations)| !violations.is_empty())
+            .collect();
+        println!(
+            "{}: {} of {} stored documents are valid",
+            name,
+            documents.len() - invalid.len(),
+            documents.len()
+        );
+        for (document, violations) in invalid.iter().take(SHOWN_VIOLATIONS) {
+            println!(
+                "  {}: {}",
+                bs58::encode(document.id).into_string(),
+                violations.join(", ")
+            );
+        }
+        if invalid.len() > SHOWN_VIOLATIONS {
+            println!("  ... {} more", invalid.len() - SHOWN_VIOLATIONS);
+        }
+    }
+}
+
+/// Applies a new version of the contract in use to the store, then checks the stored documents
+/// against it.
+pub fn prompt_upgrade(
+    input: String,
+    drive: &Drive,
+    contract: &Contract,
+    contracts: &mut ContractRegistry,
+    session: &Session,
+    rl: &mut Editor<()>,
+) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 {
+        println!("### ERROR! Two parameter should be provided");
+        return;
+    }
+    let new_path = args.get(1).unwrap();
+    let alias = match &session.current_contract {
+        Some(alias) => alias.clone(),
+        None => {
+            println!("### ERROR! No contract is in use");
+            return;
+        }
+    };
+    let old_path = match contracts.get(alias.as_str()) {
+        Some(loaded) => loaded.path.clone(),
+        None => {
+            println!("### ERROR! No contract is loaded as {}", alias);
+            return;
+        }
+    };
+
+    let diagnostics = validate_contract_file(new_path);
+    if !diagnostics.is_empty() {
+        print_diagnostics(&diagnostics);
+        return;
+    }
+    let changes = match (
+        read_contract_json(old_path.as_str()),
+        read_contract_json(new_path),
+    ) {
+        (Ok(old), Ok(new)) => diff_contracts(&old, &new),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("### ERROR! {}", e);
+            return;
+        }
+    };
+    print_changes(&changes);
+    if changes.iter().any(|change| change.breaking)
+        && !confirm(rl, "Apply the breaking changes?")
+    {
+        println!("Upgrade cancelled");
+        return;
+    }
+
+    let contract_cbor =
+        common::json_document_to_cbor(new_path, Some(rs_drive::drive::defaults::PROTOCOL_VERSION));
+    let new_contract = match Contract::from_cbor(&contract_cbor, Some(contract.id)) {
+        Ok(new_contract) => new_contract,
+        Err(e) => {
+            println!("### ERROR! Could not parse the new contract");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    let db_transaction = drive.grove.start_transaction();
+    let (storage_fee, processing_fee) = match drive.apply_contract_cbor(
+        contract_cbor,
+        Some(contract.id),
+        0f64,
+        true,
+        Some(&db_transaction),
+    ) {
+        Ok(fees) => fees,
+        Err(e) => {
+            println!("### ERROR! Drive refused the new contract");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    if let Err(e) = drive.grove.commit_transaction(db_transaction) {
+        println!("### ERROR! Unable to commit transaction");
+        println!("### Info {:?}", e);
+        return;
+    }
+    println!("Upgraded {} to {}", alias, new_path);
+    print_fees(storage_fee, processing_fee, 1);
+
+    revalidate_documents(drive, contract, &new_contract);
+    contracts.replace_contract(alias.as_str(), new_path, new_contract);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn classified(old: JsonValue, new: JsonValue) -> Vec<(String, bool)> {
+        let mut changes: Vec<(String, bool)> = diff_contracts(&old, &new)
+            .into_iter()
+            .map(|change| (change.pointer, change.breaking))
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    fn note(properties: JsonValue, required: JsonValue, indices: JsonValue) -> JsonValue {
+        json!({
+            "documents": {
+                "note": {
+                    "properties": properties,
+                    "required": required,
+                    "indices": indices,
+                    "documentsMutable": true
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn finds_no_changes_in_the_same_contract() {
+        let contract = note(
+            json!({ "title": { "type": "string", "maxLength": 63 } }),
+            json!(["title"]),
+            json!([{ "properties": [{ "title": "asc" }] }]),
+        );
+        assert!(classified(contract.clone(), contract).is_empty());
+    }
+
+    #[test]
+    fn classifies_property_changes() {
+        let old = note(
+            json!({
+                "title": { "type": "string", "maxLength": 63, "minLength": 3 },
+                "body": { "type": "string" },
+                "rating": { "type": "integer" },
+                "draft": { "type": "boolean" }
+            }),
+            json!(["title", "draft"]),
+            json!([]),
+        );
+        let new = note(
+            json!({
+                "title": { "type": "string", "maxLength": 32, "minLength": 1 },
+                "body": { "type": "string" },
+                "rating": { "type": "number" },
+                "draft": { "type": "boolean" },
+                "author": { "type": "string" },
+                "summary": { "type": "string" }
+            }),
+            json!(["title", "body", "summary"]),
+            json!([]),
+        );
+        assert_eq!(
+            classified(old, new),
+            vec![
+                ("/documents/note/properties/author".to_string(), false),
+                ("/documents/note/properties/rating".to_string(), true),
+                ("/documents/note/properties/summary".to_string(), true),
+                ("/documents/note/properties/title/maxLength".to_string(), true),
+                ("/documents/note/properties/title/minLength".to_string(), false),
+                ("/documents/note/required".to_string(), false),
+                ("/documents/note/required".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_system_fields_becoming_required() {
+        let old = note(json!({}), json!([]), json!([]));
+        let new = note(json!({}), json!(["$createdAt"]), json!([]));
+        assert_eq!(
+            classified(old.clone(), new.clone()),
+            vec![("/documents/note/required".to_string(), true)]
+        );
+        assert_eq!(
+            classified(new, old),
+            vec![("/documents/note/required".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn classifies_index_changes() {
+        let old = note(
+            json!({ "a": { "type": "integer" }, "b": { "type": "integer" } }),
+            json!([]),
+            json!([
+                { "properties": [{ "a": "asc" }] },
+                { "properties": [{ "b": "asc" }], "unique": true },
+                { "properties": [{ "a": "asc" }, { "b": "asc" }] }
+            ]),
+        );
+        let new = note(
+            json!({ "a": { "type": "integer" }, "b": { "type": "integer" } }),
+            json!([]),
+            json!([
+                { "properties": [{ "a": "asc" }], "unique": true },
+                { "properties": [{ "b": "asc" }] },
+                { "properties": [{ "b": "desc" }] }
+            ]),
+        );
+        assert_eq!(
+            classified(old, new),
+            vec![
+                ("/documents/note/indices/0/unique".to_string(), true),
+                ("/documents/note/indices/1/unique".to_string(), false),
+                ("/documents/note/indices/2".to_string(), true),
+                ("/documents/note/indices/2".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_document_type_changes() {
+        let old = json!({
+            "documents": {
+                "note": { "properties": {}, "documentsMutable": true },
+                "tag": { "properties": {} }
+            }
+        });
+        let new = json!({
+            "documents": {
+                "note": { "properties": {}, "documentsMutable": false },
+                "label": { "properties": {} }
+            }
+        });
+        assert_eq!(
+            classified(old, new),
+            vec![
+                ("/documents/label".to_string(), false),
+                ("/documents/note/documentsMutable".to_string(), true),
+                ("/documents/tag".to_string(), true),
+            ]
+        );
+    }
+}
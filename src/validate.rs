@@ -0,0 +1,358 @@
+use serde_json::{Map, Value as JsonValue};
+use std::fs;
+
+/// System fields documents always have and that indices or `required` may refer to.
+pub const SYSTEM_FIELDS: [&str; 4] = ["$id", "$ownerId", "$createdAt", "$updatedAt"];
+
+pub struct Diagnostic {
+    /// JSON pointer to the offending value
+    pub pointer: String,
+    pub message: String,
+}
+
+/// Builds a JSON pointer, escaping `~` and `/` as RFC 6901 requires.
+pub fn pointer(segments: &[&str]) -> String {
+    segments
+        .iter()
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+fn diagnostic(segments: &[&str], message: String) -> Diagnostic {
+    Diagnostic {
+        pointer: pointer(segments),
+        message,
+    }
+}
+
+/// Finds the schema of a property, dotted names like `records.dashUniqueIdentityId` are looked
+/// up in the properties of nested objects.
+fn property_schema<'a>(properties: &'a Map<String, JsonValue>, name: &str) -> Option<&'a JsonValue> {
+    let mut keys = name.split('.');
+    let mut schema = properties.get(keys.next()?)?;
+    for key in keys {
+        schema = schema.get("properties")?.get(key)?;
+    }
+    Some(schema)
+}
+
+fn validate_indexed_property(
+    schema: &JsonValue,
+    segments: &[&str],
+    name: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match schema.get("type").and_then(|schema_type| schema_type.as_str()) {
+        Some("string") => {
+            if schema.get("maxLength").is_none() {
+                diagnostics.push(diagnostic(
+                    segments,
+                    format!("indexed string property {} must set maxLength", name),
+                ));
+            }
+        }
+        Some("integer") | Some("number") | Some("boolean") | Some("date") => {}
+        Some("array") if schema.get("byteArray") == Some(&JsonValue::Bool(true)) => {
+            if schema.get("maxItems").is_none() {
+                diagnostics.push(diagnostic(
+                    segments,
+                    format!("indexed byte array property {} must set maxItems", name),
+                ));
+            }
+        }
+        Some(other) => diagnostics.push(diagnostic(
+            segments,
+            format!("property {} of type {} can not be indexed", name, other),
+        )),
+        None => diagnostics.push(diagnostic(
+            segments,
+            format!("property {} has no type", name),
+        )),
+    }
+}
+
+fn validate_document_type(
+    name: &str,
+    document_type: &JsonValue,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let properties = match document_type.get("properties").and_then(|p| p.as_object()) {
+        Some(properties) => properties,
+        None => {
+            diagnostics.push(diagnostic(
+                &["documents", name],
+                "document type must have a properties object".to_string(),
+            ));
+            return;
+        }
+    };
+
+    for (property_name, schema) in properties.iter() {
+        if schema.get("type").is_none() && schema.get("$ref").is_none() {
+            diagnostics.push(diagnostic(
+                &["documents", name, "properties", property_name.as_str()],
+                "property has no type".to_string(),
+            ));
+        }
+    }
+
+    if let Some(required) = document_type.get("required") {
+        match required.as_array() {
+            Some(required) => {
+                for (i, required_name) in required.iter().enumerate() {
+                    let i = i.to_string();
+                    let segments = ["documents", name, "required", i.as_str()];
+                    match required_name.as_str() {
+                        Some(required_name)
+                            if properties.contains_key(required_name)
+                                || SYSTEM_FIELDS.contains(&required_name) => {}
+                        Some(required_name) => diagnostics.push(diagnostic(
+                            &segments,
+                            format!("required property {} is not defined", required_name),
+                        )),
+                        None => diagnostics.push(diagnostic(
+                            &segments,
+                            "required entries must be strings".to_string(),
+                        )),
+                    }
+                }
+            }
+            None => diagnostics.push(diagnostic(
+                &["documents", name, "required"],
+                "required must be an array".to_string(),
+            )),
+        }
+    }
+
+    let indices = match document_type.get("indices") {
+        None => return,
+        Some(indices) => match indices.as_array() {
+            Some(indices) => indices,
+            None => {
+                diagnostics.push(diagnostic(
+                    &["documents", name, "indices"],
+                    "indices must be an array".to_string(),
+                ));
+                return;
+            }
+        },
+    };
+    for (i, index) in indices.iter().enumerate() {
+        let i = i.to_string();
+        let index_properties = match index.get("properties").and_then(|p| p.as_array()) {
+            Some(index_properties) if !index_properties.is_empty() => index_properties,
+            _ => {
+                diagnostics.push(diagnostic(
+                    &["documents", name, "indices", i.as_str()],
+                    "index must have a non empty properties array".to_string(),
+                ));
+                continue;
+            }
+        };
+        for (j, index_property) in index_properties.iter().enumerate() {
+            let j = j.to_string();
+            let segments = ["documents", name, "indices", i.as_str(), "properties", j.as_str()];
+            let (property_name, direction) = match index_property.as_object() {
+                Some(object) if object.len() == 1 => object.iter().next().unwrap(),
+                _ => {
+                    diagnostics.push(diagnostic(
+                        &segments,
+                        "index property must be an object with a single property name".to_string(),
+                    ));
+                    continue;
+                }
+            };
+            let segments = [
+                "documents",
+                name,
+                "indices",
+                i.as_str(),
+                "properties",
+                j.as_str(),
+                property_name.as_str(),
+            ];
+            if direction != "asc" && direction != "desc" {
+                diagnostics.push(diagnostic(
+                    &segments,
+                    "index direction must be asc or desc".to_string(),
+                ));
+            }
+            if SYSTEM_FIELDS.contains(&property_name.as_str()) {
+                continue;
+            }
+            match property_schema(properties, property_name) {
+                Some(schema) => {
+                    validate_indexed_property(schema, &segments, property_name, diagnostics)
+                }
+                None => diagnostics.push(diagnostic(
+                    &segments,
+                    format!("index property {} is not defined in properties", property_name),
+                )),
+            }
+        }
+    }
+}
+
+/// Validates a single document type, for checking one before the whole contract exists.
+pub fn validate_document_type_json(name: &str, document_type: &JsonValue) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    validate_document_type(name, document_type, &mut diagnostics);
+    diagnostics
+}
+
+pub fn validate_contract_json(contract: &JsonValue) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    match contract.get("documents").and_then(|documents| documents.as_object()) {
+        Some(documents) if !documents.is_empty() => {
+            for (name, document_type) in documents.iter() {
+                validate_document_type(name, document_type, &mut diagnostics);
+            }
+        }
+        _ => diagnostics.push(diagnostic(
+            &["documents"],
+            "contract must have a non empty documents object".to_string(),
+        )),
+    }
+    diagnostics
+}
+
+pub fn validate_contract_file(path: &str) -> Vec<Diagnostic> {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) => {
+            return vec![Diagnostic {
+                pointer: "".to_string(),
+                message: format!("could not read {}: {}", path, e),
+            }]
+        }
+    };
+    match serde_json::from_str::<JsonValue>(data.as_str()) {
+        Ok(contract) => validate_contract_json(&contract),
+        Err(e) => vec![Diagnostic {
+            pointer: "".to_string(),
+            message: format!("malformed json at line {} column {}: {}", e.line(), e.column(), e),
+        }],
+    }
+}
+
+pub fn print_diagnostics(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics.iter() {
+        println!("### ERROR! {} : {}", diagnostic.pointer, diagnostic.message);
+    }
+}
+
+pub fn prompt_validate(input: String) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 2 {
+        println!("### ERROR! Two parameter should be provided");
+        return;
+    }
+    let diagnostics = validate_contract_file(args.get(1).unwrap());
+    if diagnostics.is_empty() {
+        println!("Contract is valid");
+    } else {
+        print_diagnostics(&diagnostics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pointers(contract: &JsonValue) -> Vec<String> {
+        validate_contract_json(contract)
+            .into_iter()
+            .map(|diagnostic| diagnostic.pointer)
+            .collect()
+    }
+
+    #[test]
+    fn escapes_pointer_segments() {
+        assert_eq!(pointer(&[]), "");
+        assert_eq!(pointer(&["documents", "note"]), "/documents/note");
+        assert_eq!(pointer(&["a/b", "c~d"]), "/a~1b/c~0d");
+    }
+
+    #[test]
+    fn accepts_a_valid_contract() {
+        let contract = json!({
+            "documents": {
+                "note": {
+                    "properties": {
+                        "title": { "type": "string", "maxLength": 63 },
+                        "records": {
+                            "type": "object",
+                            "properties": {
+                                "identity": { "type": "array", "byteArray": true, "maxItems": 32 }
+                            }
+                        }
+                    },
+                    "required": ["title", "$createdAt"],
+                    "indices": [
+                        { "properties": [{ "title": "asc" }, { "$ownerId": "desc" }] },
+                        { "properties": [{ "records.identity": "asc" }], "unique": true }
+                    ]
+                }
+            }
+        });
+        assert!(pointers(&contract).is_empty());
+    }
+
+    #[test]
+    fn points_at_the_offending_values() {
+        let contract = json!({
+            "documents": {
+                "note": {
+                    "properties": {
+                        "title": { "type": "string" },
+                        "body": {},
+                        "tags": { "type": "array" }
+                    },
+                    "required": ["title", "subtitle", 3],
+                    "indices": [
+                        { "properties": [{ "title": "up" }] },
+                        { "properties": [{ "tags": "asc" }, { "missing": "asc" }] },
+                        { "properties": [] }
+                    ]
+                }
+            }
+        });
+        assert_eq!(
+            pointers(&contract),
+            vec![
+                "/documents/note/properties/body",
+                "/documents/note/required/1",
+                "/documents/note/required/2",
+                "/documents/note/indices/0/properties/0/title",
+                "/documents/note/indices/0/properties/0/title",
+                "/documents/note/indices/1/properties/0/tags",
+                "/documents/note/indices/1/properties/1/missing",
+                "/documents/note/indices/2",
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_property_names_in_pointers() {
+        let contract = json!({
+            "documents": {
+                "a/b": { "properties": { "x~y": {} } }
+            }
+        });
+        assert_eq!(pointers(&contract), vec!["/documents/a~1b/properties/x~0y"]);
+        assert_eq!(pointers(&json!({ "documents": {} })), vec!["/documents"]);
+    }
+
+    #[test]
+    fn validates_a_single_document_type() {
+        let document_type = json!({
+            "properties": { "title": { "type": "string" } },
+            "indices": [{ "properties": [{ "title": "asc" }] }]
+        });
+        let diagnostics = validate_document_type_json("note", &document_type);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].pointer, "/documents/note/indices/0/properties/0/title");
+        assert_eq!(diagnostics[0].message, "indexed string property title must set maxLength");
+    }
+}
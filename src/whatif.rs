@@ -0,0 +1,335 @@
+use crate::contract::{all_documents, document_value, index_name, populate_with_documents};
+use crate::session::Session;
+use ciborium::ser::into_writer;
+use ciborium::value::Value;
+use rand::seq::SliceRandom;
+use rs_drive::contract::types::{DocumentField, DocumentFieldType};
+use rs_drive::contract::{document::Document, Contract, DocumentType, Index, IndexProperty};
+use rs_drive::drive::Drive;
+use rs_drive::query::DriveQuery;
+use std::collections::BTreeMap;
+
+enum IndexChange {
+    Add(Index),
+    Remove(Vec<String>),
+}
+
+/// Finds a property of the document type, dotted names like `records.dashUniqueIdentityId` are
+/// looked up in the properties of nested objects.
+fn property_field<'a>(
+    properties: &'a BTreeMap<String, DocumentField>,
+    name: &str,
+) -> Option<&'a DocumentField> {
+    if let Some(field) = properties.get(name) {
+        return Some(field);
+    }
+    let mut keys = name.split('.');
+    let mut field = properties.get(keys.next()?)?;
+    for key in keys {
+        field = match &field.document_type {
+            DocumentFieldType::Object(properties) => properties.get(key)?,
+            _ => return None,
+        };
+    }
+    Some(field)
+}
+
+/// Parses `+a,b` to add an index, `+!a,b` to add a unique index and `-a,b` to remove one.
+fn parse_index_spec(spec: &str, document_type: &DocumentType) -> Result<IndexChange, String> {
+    let (adding, rest) = if let Some(rest) = spec.strip_prefix('+') {
+        (true, rest)
+    } else if let Some(rest) = spec.strip_prefix('-') {
+        (false, rest)
+    } else {
+        return Err("the index spec must start with + or -".to_string());
+    };
+    let (unique, rest) = match rest.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let names: Vec<String> = rest
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect();
+    if names.is_empty() {
+        return Err("the index spec must name at least one property".to_string());
+    }
+    for name in names.iter() {
+        if !name.starts_with('$') && property_field(&document_type.properties, name).is_none() {
+            return Err(format!("property {} does not exist", name));
+        }
+    }
+    if adding {
+        Ok(IndexChange::Add(Index {
+            properties: names
+                .into_iter()
+                .map(|name| IndexProperty {
+                    name,
+                    ascending: true,
+                })
+                .collect(),
+            unique,
+        }))
+    } else {
+        Ok(IndexChange::Remove(names))
+    }
+}
+
+fn apply_index_change(
+    document_type: &DocumentType,
+    change: &IndexChange,
+) -> Result<DocumentType, String> {
+    let mut changed = document_type.clone();
+    match change {
+        IndexChange::Add(index) => {
+            if changed
+                .indices
+                .iter()
+                .any(|existing| index_name(existing) == index_name(index))
+            {
+                return Err("the document type already has this index".to_string());
+            }
+            changed.indices.push(index.clone());
+        }
+        IndexChange::Remove(names) => {
+            let len = changed.indices.len();
+            let name = names.join("/");
+            changed
+                .indices
+                .retain(|existing| index_name(existing) != name);
+            if changed.indices.len() == len {
+                return Err("the document type has no such index".to_string());
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Rough size of the entries an index adds for a document: the property names and values on
+/// the way down, the `0` tree and the reference to the document.
+fn estimated_index_entry_size(
+    document_type_name: &str,
+    index: &Index,
+    document: &Document,
+) -> u64 {
+    let mut size = 0;
+    for property in index.properties.iter() {
+        size += property.name.len();
+        size += match document_value(document, property.name.as_str()) {
+            None => 0,
+            Some(Value::Text(text)) => text.len(),
+            Some(Value::Bytes(bytes)) => bytes.len(),
+            Some(Value::Integer(_)) | Some(Value::Float(_)) => 8,
+            Some(Value::Bool(_)) => 1,
+            Some(value) => {
+                let mut buffer = vec![];
+                into_writer(&value, &mut buffer).expect("expected to serialize value");
+                buffer.len()
+            }
+        };
+    }
+    // the 0 key, the document id key and the reference path to the primary key tree
+    size += 1 + 32 + (1 + 32 + 1 + document_type_name.len() + 1 + 32);
+    size as u64
+}
+
+/// A query can be answered if it goes through the primary key or an index matches its where
+/// and order by fields.
+fn query_servable(query: &DriveQuery) -> bool {
+    let internal_clauses = &query.internal_clauses;
+    if internal_clauses.primary_key_equal_clause.is_some()
+        || internal_clauses.primary_key_in_clause.is_some()
+    {
+        return true;
+    }
+    let mut fields: Vec<&str> = internal_clauses
+        .equal_clauses
+        .keys()
+        .map(|field| field.as_str())
+        .collect();
+    let in_field = internal_clauses
+        .in_clause
+        .as_ref()
+        .map(|in_clause| in_clause.field.as_str());
+    if let Some(in_field) = in_field {
+        fields.push(in_field);
+    }
+    if let Some(range_clause) = &internal_clauses.range_clause {
+        fields.push(range_clause.field.as_str());
+    }
+    let order_by: Vec<&str> = query.order_by.keys().map(|field| field.as_str()).collect();
+    if fields.is_empty() && order_by.is_empty() {
+        return true;
+    }
+    query
+        .document_type
+        .index_for_types(fields.as_slice(), in_field, order_by.as_slice())
+        .is_some()
+}
+
+pub fn prompt_save_query(input: String, session: &mut Session) {
+    match input.split_once(' ') {
+        Some((_, query)) if !query.trim().is_empty() => {
+            session.saved_queries.push(query.trim().to_string());
+            println!("Saved query {}", session.saved_queries.len());
+        }
+        _ => println!("### ERROR! A query should be provided"),
+    }
+}
+
+pub fn print_saved_queries(session: &Session) {
+    if session.saved_queries.is_empty() {
+        println!("No saved queries, add one with save <sqlQuery>");
+    }
+    for (i, query) in session.saved_queries.iter().enumerate() {
+        println!("{}: {}", i + 1, query);
+    }
+}
+
+fn print_fee_change(name: &str, before: i128, after: i128) {
+    println!("{}: {} -> {} ({:+})", name, before, after, after - before);
+}
+
+pub fn prompt_whatif(input: String, drive: &Drive, contract: &Contract, session: &Session) {
+    let args: Vec<&str> = input.split_whitespace().collect();
+    if args.len() != 3 && args.len() != 4 {
+        println!("### ERROR! A document type, an index spec and optionally a sample size should be provided");
+        return;
+    }
+    let document_type_name = args.get(1).unwrap();
+    let document_type = match contract.document_type_for_name(document_type_name) {
+        Ok(document_type) => document_type,
+        Err(_) => {
+            println!("### ERROR! Document type does not exist");
+            return;
+        }
+    };
+    let change = match parse_index_spec(args.get(2).unwrap(), document_type) {
+        Ok(change) => change,
+        Err(e) => {
+            println!("### ERROR! {}", e);
+            return;
+        }
+    };
+    let changed_document_type = match apply_index_change(document_type, &change) {
+        Ok(changed_document_type) => changed_document_type,
+        Err(e) => {
+            println!("### ERROR! {}", e);
+            return;
+        }
+    };
+    let mut changed_contract = contract.clone();
+    changed_contract
+        .document_types
+        .insert(document_type_name.to_string(), changed_document_type);
+
+    let mut documents = match all_documents(drive, contract, document_type) {
+        Ok(documents) => documents,
+        Err(e) => {
+            println!("### ERROR! Could not fetch documents");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    if let Some(sample_str) = args.get(3) {
+        match sample_str.parse::<usize>() {
+            Ok(sample) => {
+                documents.shuffle(&mut rand::thread_rng());
+                documents.truncate(sample);
+            }
+            Err(_) => {
+                println!("### ERROR! The sample size was not an integer");
+                return;
+            }
+        }
+    }
+    if documents.is_empty() {
+        println!("### ERROR! There are no stored documents to replay, populate first");
+        return;
+    }
+    let count = documents.len();
+
+    let (storage_fee, processing_fee) =
+        match populate_with_documents(
+            documents.clone(),
+            drive,
+            document_type,
+            contract,
+            false,
+            session,
+        ) {
+            Ok(fees) => fees,
+            Err(e) => {
+                println!("### ERROR! Could not estimate the current costs");
+                println!("### Info {:?}", e);
+                return;
+            }
+        };
+    let changed_document_type = changed_contract
+        .document_type_for_name(document_type_name)
+        .expect("expected the changed document type");
+    let (changed_storage_fee, changed_processing_fee) = match populate_with_documents(
+        documents.clone(),
+        drive,
+        changed_document_type,
+        &changed_contract,
+        false,
+        session,
+    ) {
+        Ok(fees) => fees,
+        Err(e) => {
+            println!("### ERROR! Could not estimate the costs with the index change");
+            println!("### Info {:?}", e);
+            return;
+        }
+    };
+    let index_bytes: u64 = match &change {
+        IndexChange::Add(index) => documents
+            .iter()
+            .map(|document| estimated_index_entry_size(document_type_name, index, document))
+            .sum(),
+        IndexChange::Remove(names) => document_type
+            .indices
+            .iter()
+            .filter(|index| index_name(index) == names.join("/"))
+            .flat_map(|index| {
+                documents.iter().map(move |document| {
+                    estimated_index_entry_size(document_type_name, index, document)
+                })
+            })
+            .sum(),
+    };
+
+    println!("Replayed {} documents without applying them", count);
+    print_fee_change("Storage fee", storage_fee as i128, changed_storage_fee as i128);
+    print_fee_change(
+        "Processing fee",
+        processing_fee as i128,
+        changed_processing_fee as i128,
+    );
+    let sign = match change {
+        IndexChange::Add(_) => "+",
+        IndexChange::Remove(_) => "-",
+    };
+    println!(
+        "Index bytes: about {}{} ({} each)",
+        sign,
+        index_bytes,
+        index_bytes / count as u64
+    );
+
+    for (i, sql) in session.saved_queries.iter().enumerate() {
+        let before = DriveQuery::from_sql_expr(sql.as_str(), contract)
+            .map_or(false, |query| query_servable(&query));
+        let after = DriveQuery::from_sql_expr(sql.as_str(), &changed_contract)
+            .map_or(false, |query| query_servable(&query));
+        let status = match (before, after) {
+            (false, true) => "becomes servable",
+            (true, false) => "becomes unservable",
+            (true, true) => "servable",
+            (false, false) => "unservable",
+        };
+        println!("Query {} {}: {}", i + 1, status, sql);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::validate::{print_diagnostics, validate_contract_json, validate_document_type_json};
+use rand::{Rng, SeedableRng};
+use rustyline::Editor;
+use serde_json::{json, Map, Value as JsonValue};
+use std::fs;
+
+const PROPERTY_TYPES: [&str; 6] = ["string", "integer", "number", "boolean", "bytes", "date"];
+
+/// Reads a trimmed line, `None` when the user interrupted the wizard.
+fn ask(rl: &mut Editor<()>, question: &str) -> Option<String> {
+    rl.readline(question)
+        .ok()
+        .map(|answer| answer.trim().to_string())
+}
+
+fn ask_yes_no(rl: &mut Editor<()>, question: &str) -> Option<bool> {
+    let answer = ask(rl, question)?;
+    Some(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+fn ask_optional_number(rl: &mut Editor<()>, question: &str) -> Option<Option<u64>> {
+    loop {
+        let answer = ask(rl, question)?;
+        if answer.is_empty() {
+            return Some(None);
+        }
+        match answer.parse::<u64>() {
+            Ok(value) => return Some(Some(value)),
+            Err(_) => println!("### ERROR! An integer was not provided"),
+        }
+    }
+}
+
+fn ask_property(rl: &mut Editor<()>, property_name: &str) -> Option<(JsonValue, bool)> {
+    let property_type = loop {
+        let answer = ask(
+            rl,
+            format!("  Type of {} [{}]: ", property_name, PROPERTY_TYPES.join("/")).as_str(),
+        )?;
+        if PROPERTY_TYPES.contains(&answer.as_str()) {
+            break answer;
+        }
+        println!("### ERROR! Unknown type {}", answer);
+    };
+    let schema = match property_type.as_str() {
+        "string" => {
+            let mut schema = json!({ "type": "string" });
+            if let Some(max_length) = ask_optional_number(rl, "  maxLength (empty for none): ")? {
+                schema["maxLength"] = json!(max_length);
+            }
+            schema
+        }
+        "bytes" => {
+            let mut schema = json!({ "type": "array", "byteArray": true });
+            if let Some(min_items) = ask_optional_number(rl, "  minItems (empty for none): ")? {
+                schema["minItems"] = json!(min_items);
+            }
+            if let Some(max_items) = ask_optional_number(rl, "  maxItems (empty for none): ")? {
+                schema["maxItems"] = json!(max_items);
+            }
+            schema
+        }
+        other => json!({ "type": other }),
+    };
+    let required = ask_yes_no(rl, "  Required [y/N]: ")?;
+    Some((schema, required))
+}
+
+fn document_type_json(
+    properties: &Map<String, JsonValue>,
+    required: &[JsonValue],
+    indices: &[JsonValue],
+) -> JsonValue {
+    json!({
+        "indices": indices,
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// Validates the document type with the entry just added, printing what is wrong with it.
+/// Entries added before were already checked, so any diagnostic is about the new one.
+fn accepts_entry(document_type_name: &str, document_type: &JsonValue) -> bool {
+    let diagnostics = validate_document_type_json(document_type_name, document_type);
+    if diagnostics.is_empty() {
+        return true;
+    }
+    print_diagnostics(&diagnostics);
+    false
+}
+
+fn ask_document_type(rl: &mut Editor<()>, document_type_name: &str) -> Option<JsonValue> {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    loop {
+        let property_name = ask(
+            rl,
+            format!("Property of {} (empty to finish): ", document_type_name).as_str(),
+        )?;
+        if property_name.is_empty() {
+            if properties.is_empty() {
+                println!("### ERROR! At least one property should be provided");
+                continue;
+            }
+            break;
+        }
+        if properties.contains_key(&property_name) {
+            println!("### ERROR! Property {} is already defined", property_name);
+            continue;
+        }
+        loop {
+            let (schema, is_required) = ask_property(rl, property_name.as_str())?;
+            let mut candidate_properties = properties.clone();
+            candidate_properties.insert(property_name.clone(), schema.clone());
+            let mut candidate_required = required.clone();
+            if is_required {
+                candidate_required.push(JsonValue::String(property_name.clone()));
+            }
+            let candidate = document_type_json(&candidate_properties, &candidate_required, &[]);
+            if accepts_entry(document_type_name, &candidate) {
+                properties = candidate_properties;
+                required = candidate_required;
+                break;
+            }
+            println!("Enter {} again", property_name);
+        }
+    }
+
+    let mut indices = vec![];
+    loop {
+        let index_properties = ask(
+            rl,
+            format!(
+                "Index of {}, comma separated properties (empty to finish): ",
+                document_type_name
+            )
+            .as_str(),
+        )?;
+        if index_properties.is_empty() {
+            break;
+        }
+        let index_properties: Vec<JsonValue> = index_properties
+            .split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let mut index_property = Map::new();
+                index_property.insert(name.to_string(), json!("asc"));
+                JsonValue::Object(index_property)
+            })
+            .collect();
+        let mut candidate_indices = indices.clone();
+        candidate_indices.push(json!({ "properties": index_properties, "unique": false }));
+        let candidate = document_type_json(&properties, &required, &candidate_indices);
+        if !accepts_entry(document_type_name, &candidate) {
+            println!("Enter the index again");
+            continue;
+        }
+        let unique = ask_yes_no(rl, "  Unique [y/N]: ")?;
+        indices.push(json!({ "properties": index_properties, "unique": unique }));
+    }
+
+    Some(document_type_json(&properties, &required, &indices))
+}
+
+/// Asks for document types, their properties and indices, then writes the contract json.
+/// Returns the path of the written file.
+pub fn contract_wizard(rl: &mut Editor<()>) -> Option<String> {
+    let contract_path = ask(rl, "Contract file path: ")?;
+    if contract_path.is_empty() {
+        println!("### ERROR! A path should be provided");
+        return None;
+    }
+
+    let mut documents = Map::new();
+    loop {
+        let document_type_name = ask(rl, "Document type name (empty to finish): ")?;
+        if document_type_name.is_empty() {
+            if documents.is_empty() {
+                println!("### ERROR! At least one document type should be provided");
+                continue;
+            }
+            break;
+        }
+        let document_type = ask_document_type(rl, document_type_name.as_str())?;
+        documents.insert(document_type_name, document_type);
+    }
+
+    let mut rng = rand::rngs::StdRng::from_entropy();
+    let contract = json!({
+        "$id": bs58::encode(rng.gen::<[u8; 32]>()).into_string(),
+        "documents": documents,
+    });
+    let diagnostics = validate_contract_json(&contract);
+    if !diagnostics.is_empty() {
+        print_diagnostics(&diagnostics);
+        println!("### ERROR! Contract was not written");
+        return None;
+    }
+    let contract_json =
+        serde_json::to_string_pretty(&contract).expect("expected to serialize contract");
+    match fs::write(contract_path.as_str(), contract_json) {
+        Ok(_) => {
+            println!("Contract written to {}", contract_path);
+            Some(contract_path)
+        }
+        Err(e) => {
+            println!("### ERROR! Could not write {}", contract_path);
+            println!("### Info {:?}", e);
+            None
+        }
+    }
+}